@@ -13,8 +13,11 @@ pub enum Level {
 
 impl Level {
     pub fn from(puzzle: &puzzle::Puzzle) -> Self {
-        let stats = analyzer::Stats::from(puzzle);
+        Self::from_stats(&analyzer::Stats::from(puzzle))
+    }
 
+    /// Determine the level from the statistics of solving with all the tactics.
+    pub fn from_stats(stats: &analyzer::Stats) -> Self {
         if stats.count(tactics::Tactics::Backtrack).unwrap() > 1 {
             return Level::Inhuman;
         }
@@ -36,6 +39,7 @@ impl Level {
         let mut tactics = vec![
             tactics::Tactics::Row2,
             tactics::Tactics::Row3,
+            tactics::Tactics::Markers,
             tactics::Tactics::CountFixed,
         ];
         if *self >= Level::Medium {
@@ -68,6 +72,7 @@ mod tests {
                 == vec![
                     tactics::Tactics::Row2,
                     tactics::Tactics::Row3,
+                    tactics::Tactics::Markers,
                     tactics::Tactics::CountFixed,
                 ]
         );
@@ -77,6 +82,7 @@ mod tests {
                 == vec![
                     tactics::Tactics::Row2,
                     tactics::Tactics::Row3,
+                    tactics::Tactics::Markers,
                     tactics::Tactics::CountFixed,
                     tactics::Tactics::CountGuess,
                     tactics::Tactics::Uniqueness,
@@ -88,6 +94,7 @@ mod tests {
                 == vec![
                     tactics::Tactics::Row2,
                     tactics::Tactics::Row3,
+                    tactics::Tactics::Markers,
                     tactics::Tactics::CountFixed,
                     tactics::Tactics::CountGuess,
                     tactics::Tactics::Uniqueness,
@@ -100,6 +107,7 @@ mod tests {
                 == vec![
                     tactics::Tactics::Row2,
                     tactics::Tactics::Row3,
+                    tactics::Tactics::Markers,
                     tactics::Tactics::CountFixed,
                     tactics::Tactics::CountGuess,
                     tactics::Tactics::Uniqueness,
@@ -1,10 +1,8 @@
-use crate::{
-    analyzer::tactics::{hint::Hint, Tactic},
-    puzzle,
-};
+use crate::{analyzer::tactics::hint::Hint, puzzle};
 use std::result;
 
 pub mod level;
+pub mod propagation;
 pub mod tactics;
 
 #[derive(Debug, PartialEq, Eq)]
@@ -39,29 +37,28 @@ impl Stats {
             })
             .collect();
 
-        // apply the tactics in a loop
-        let mut solved = puzzle.clone();
+        // apply the tactics in a loop, only analyzing the changed lines again
+        let mut propagation = propagation::Propagation::new(puzzle, tactics);
         let mut tactic_index = 0;
         loop {
             if tactic_index == tactics.len() {
-                return Stats::new(counters, puzzle.clone(), solved);
+                return Stats::new(counters, puzzle.clone(), propagation.puzzle().clone());
             }
 
-            let hints = tactics[tactic_index].hints(&solved);
+            let hints = propagation.hints(tactic_index);
             if hints.len() == 0 {
                 tactic_index += 1;
             } else {
                 if tactics[tactic_index] <= tactics::Tactics::CountFixed {
                     // apply all hints if it is a basic tactic
                     for Hint { x, y, v } in hints {
-                        solved[y][x] = Some(v);
+                        propagation.set(x, y, v);
                         counters[tactic_index].count += 1;
                     }
                 } else {
                     // apply only one hint if it is an advanced tactic
-                    // TODO apply the best hint
-                    let Hint { x, y, v } = hints[0];
-                    solved[y][x] = Some(v);
+                    let Hint { x, y, v } = choose(propagation.puzzle(), &hints);
+                    propagation.set(x, y, v);
                     counters[tactic_index].count += 1;
                 }
                 tactic_index = 0;
@@ -88,9 +85,82 @@ impl Stats {
     }
 }
 
+/// Choose a hint in a way which does not depend on how the puzzle is rotated,
+/// mirrored or whether the symbols are swapped, such that the statistics do not
+/// either. The hints are compared on a cheap key first, and only the hints which
+/// tie on it are compared on the canonical puzzle they lead to.
+pub(crate) fn choose(puzzle: &puzzle::Puzzle, hints: &[Hint]) -> Hint {
+    // the canonical form does not transpose a rectangular puzzle, so the hints
+    // are compared on the puzzle turned on its side when it is taller than wide
+    let transposed = puzzle.height() > puzzle.width();
+    let landscape = match transposed {
+        true => puzzle.transpose(),
+        false => puzzle.clone(),
+    };
+    let (width, height) = (landscape.width(), landscape.height());
+    let place = |hint: &Hint| match transposed {
+        true => (hint.y, hint.x),
+        false => (hint.x, hint.y),
+    };
+
+    let mut counts = vec![0; landscape.rules().symbols as usize];
+    for y in 0..height {
+        for v in landscape[y].iter().flatten() {
+            counts[*v as usize] += 1;
+        }
+    }
+
+    // the distances of the cell to the edges and how often the value is used in
+    // the puzzle and in the lines through the cell, where the distances and the
+    // lines are unordered when a square puzzle can be transposed
+    let key = |hint: &Hint| {
+        let (x, y) = place(hint);
+        let edges = (x.min(width - 1 - x), y.min(height - 1 - y));
+        let lines = (
+            landscape[y].iter().filter(|&&v| v == Some(hint.v)).count(),
+            (0..height)
+                .filter(|&j| landscape[j][x] == Some(hint.v))
+                .count(),
+        );
+        let (edges, lines) = match width == height {
+            true => (sort(edges), sort(lines)),
+            false => (edges, lines),
+        };
+        (edges, counts[hint.v as usize], lines)
+    };
+    let keys: Vec<_> = hints.iter().map(key).collect();
+    let best = keys.iter().min().unwrap();
+    let ties: Vec<_> = hints
+        .iter()
+        .zip(&keys)
+        .filter(|(_, key)| *key == best)
+        .map(|(hint, _)| *hint)
+        .collect();
+    if ties.len() == 1 {
+        return ties[0];
+    }
+
+    return ties
+        .into_iter()
+        .min_by_key(|hint| {
+            let (x, y) = place(hint);
+            let mut next = landscape.clone();
+            next[y][x] = Some(hint.v);
+            next.canonical()
+        })
+        .unwrap();
+}
+
+/// Return the pair with the smallest element first.
+fn sort(pair: (usize, usize)) -> (usize, usize) {
+    return (pair.0.min(pair.1), pair.0.max(pair.1));
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generator;
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn stats_creation_list() {
@@ -200,7 +270,7 @@ mod tests {
                     counters: vec![
                         Counter {
                             tactic: tactics::Tactics::Row2,
-                            count: 3,
+                            count: 2,
                         },
                         Counter {
                             tactic: tactics::Tactics::Row3,
@@ -208,7 +278,7 @@ mod tests {
                         },
                         Counter {
                             tactic: tactics::Tactics::CountFixed,
-                            count: 1,
+                            count: 2,
                         },
                         Counter {
                             tactic: tactics::Tactics::CountGuess,
@@ -224,4 +294,55 @@ mod tests {
                 }
         );
     }
+
+    #[test]
+    fn transform_invariance() {
+        let puzzles = [
+            ("b1c1b0h11d0a01h", 6, 6),
+            ("a0d1g1a0a1h1b1a00b", 6, 6),
+            ("11k1i101a1e1b", 6, 6),
+            ("e1a1a1c1h0a", 6, 4),
+            ("11a1a0a0b0c00a1e0a1b01c0c01b1c0d00a01a0a11", 10, 6),
+        ];
+        let all = level::Level::Inhuman.tactics();
+
+        for (codex, width, height) in puzzles {
+            let puzzle = puzzle::Puzzle::from_codex(codex, width, height).unwrap();
+            let stats = Stats::from(&puzzle);
+            for image in [
+                puzzle.rotate(),
+                puzzle.transpose(),
+                puzzle.flip_horizontal(),
+                puzzle.flip_vertical(),
+                puzzle.swap(),
+            ] {
+                let image_stats = Stats::from(&image);
+                for tactic in &all {
+                    assert_eq!(image_stats.count(*tactic), stats.count(*tactic));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn transform_invariance_rectangular() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let all = level::Level::Inhuman.tactics();
+
+        for _ in 0..6 {
+            let width = 2 * rng.gen_range(4..=6);
+            let height = 2 * rng.gen_range(3..width / 2);
+            let seed = rng.gen();
+            let puzzle = generator::gen(width, height, level::Level::Medium, Some(seed)).unwrap();
+            for level in [level::Level::Medium, level::Level::Inhuman] {
+                let stats = Stats::from_level(&puzzle, level);
+                for image in [puzzle.rotate(), puzzle.transpose()] {
+                    let image_stats = Stats::from_level(&image, level);
+                    for tactic in &all {
+                        assert_eq!(image_stats.count(*tactic), stats.count(*tactic));
+                    }
+                }
+            }
+        }
+    }
 }
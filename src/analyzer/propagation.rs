@@ -0,0 +1,248 @@
+use crate::{
+    analyzer::tactics::{
+        self,
+        hint::Hint,
+        line::{self, Line},
+        Tactic,
+    },
+    puzzle,
+};
+use std::collections::VecDeque;
+
+/// Keeps track of the hints every tactic gives on a puzzle while the puzzle is
+/// being filled in. The hints of the line tactics are cached per line, when a
+/// cell changes only its row and column, and the diagonals it is on if they
+/// follow rules, are queued to be analyzed again.
+pub struct Propagation<'a> {
+    puzzle: puzzle::Puzzle,
+    tactics: &'a [tactics::Tactics],
+    lines: Vec<Line>,            // the rows, then the columns and the diagonals
+    cache: Vec<Vec<Vec<Hint>>>,  // the hints per tactic per line
+    queue: Vec<VecDeque<usize>>, // the lines to analyze again per tactic
+    queued: Vec<Vec<bool>>,      // whether a line is in the queue per tactic
+}
+
+impl<'a> Propagation<'a> {
+    /// Start the propagation on the given puzzle, with all the lines queued.
+    pub fn new(puzzle: &puzzle::Puzzle, tactics: &'a [tactics::Tactics]) -> Self {
+        let all = puzzle::line::all(puzzle);
+        let lines = all.len();
+
+        Propagation {
+            puzzle: puzzle.clone(),
+            lines: all,
+            tactics,
+            cache: vec![vec![Vec::new(); lines]; tactics.len()],
+            queue: tactics
+                .iter()
+                .map(|tactic| {
+                    if tactic.local() {
+                        (0..lines).collect()
+                    } else {
+                        VecDeque::new()
+                    }
+                })
+                .collect(),
+            queued: vec![vec![true; lines]; tactics.len()],
+        }
+    }
+
+    /// Return the puzzle with all the changes made so far.
+    pub fn puzzle(&self) -> &puzzle::Puzzle {
+        &self.puzzle
+    }
+
+    /// Return all the hints the tactic at the given index gives on the current
+    /// puzzle, only the queued lines are analyzed again.
+    pub fn hints(&mut self, index: usize) -> Vec<Hint> {
+        let tactic = self.tactics[index];
+        if !tactic.local() {
+            return tactic.hints(&self.puzzle);
+        }
+
+        while let Some(i) = self.queue[index].pop_front() {
+            self.queued[index][i] = false;
+            self.cache[index][i] = tactic.line_hints(&self.puzzle, self.line(i)).unwrap();
+        }
+
+        let (rows, columns) = self.cache[index].split_at(self.puzzle.height());
+        return tactic.combine(&self.puzzle, rows, columns);
+    }
+
+    /// Fill in the cell and queue the lines which are affected by the change.
+    pub fn set(&mut self, x: usize, y: usize, v: u8) {
+        self.puzzle[y][x] = Some(v);
+
+        let through = line::through(&self.puzzle, x, y);
+        for index in 0..self.tactics.len() {
+            if !self.tactics[index].local() {
+                continue;
+            }
+            for l in &through {
+                self.enqueue(index, self.index(*l));
+            }
+
+            // a full line can change the hints on all the lines next to it
+            if self.tactics[index].parallel() {
+                for l in &through {
+                    if l.full(&self.puzzle) {
+                        for other in l.parallel(&self.puzzle) {
+                            self.enqueue(index, self.index(other));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Queue the line with the given index for the tactic at the given index.
+    fn enqueue(&mut self, index: usize, i: usize) {
+        if !self.queued[index][i] {
+            self.queued[index][i] = true;
+            self.queue[index].push_back(i);
+        }
+    }
+
+    /// Return the line with the given index, the rows come before the columns
+    /// and the columns before the diagonals.
+    fn line(&self, i: usize) -> Line {
+        self.lines[i]
+    }
+
+    /// Return the index of the given line.
+    fn index(&self, line: Line) -> usize {
+        self.lines.iter().position(|&l| l == line).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analyzer::{self, level},
+        solver,
+    };
+    use rand::{Rng, SeedableRng};
+
+    /// Apply the tactics by analyzing the whole puzzle again after every change,
+    /// the propagation should give the same statistics.
+    fn reference(puzzle: &puzzle::Puzzle, tactics: &[tactics::Tactics]) -> analyzer::Stats {
+        let mut counters: Vec<_> = tactics
+            .iter()
+            .map(|tactic| analyzer::Counter {
+                tactic: *tactic,
+                count: 0,
+            })
+            .collect();
+
+        let mut solved = puzzle.clone();
+        let mut tactic_index = 0;
+        while tactic_index < tactics.len() {
+            let hints = tactics[tactic_index].hints(&solved);
+            if hints.is_empty() {
+                tactic_index += 1;
+                continue;
+            }
+
+            let hints = if tactics[tactic_index] <= tactics::Tactics::CountFixed {
+                hints
+            } else {
+                vec![analyzer::choose(&solved, &hints)]
+            };
+            for hint in hints {
+                solved[hint.y][hint.x] = Some(hint.v);
+                counters[tactic_index].count += 1;
+            }
+            tactic_index = 0;
+        }
+
+        analyzer::Stats {
+            counters,
+            unsolved: puzzle.clone(),
+            solved,
+        }
+    }
+
+    #[test]
+    fn same_stats() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let solutions = [
+            ("011001110010101100001011010110100101", 6, 6),
+            (
+                "1010011010101100010100110100110110110010110011000101100100110011",
+                8,
+                8,
+            ),
+            ("01101001100101100110100110010110", 8, 4),
+        ];
+
+        for (codex, width, height) in solutions {
+            let solution = puzzle::Puzzle::from_codex(codex, width, height).unwrap();
+            for _ in 0..20 {
+                let mut puzzle = solution.clone();
+                for y in 0..height {
+                    for x in 0..width {
+                        if rng.gen_bool(0.5) {
+                            puzzle[y][x] = None;
+                        }
+                    }
+                }
+
+                // the advanced tactics assume there is only one solution
+                let mut levels = vec![level::Level::Easy];
+                if solver::unique(&puzzle) == Some(true) {
+                    levels.push(level::Level::Hard);
+                }
+
+                for level in levels {
+                    assert_eq!(
+                        analyzer::Stats::from_level(&puzzle, level),
+                        reference(&puzzle, &level.tactics())
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn diagonals() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let rules = puzzle::rules::Rules::diagonal();
+        let solution = solver::solve(&puzzle::Puzzle::new_with(6, 6, rules).unwrap()).unwrap();
+        for _ in 0..10 {
+            let mut puzzle = solution.clone();
+            for y in 0..6 {
+                for x in 0..6 {
+                    if rng.gen_bool(0.6) {
+                        puzzle[y][x] = None;
+                    }
+                }
+            }
+
+            let tactics = level::Level::Medium.tactics();
+            let mut propagation = Propagation::new(&puzzle, &tactics);
+            assert_eq!(propagation.cache[0].len(), 14);
+            propagation.hints(0);
+            propagation.set(2, 3, solution[3][2].unwrap());
+            assert_eq!(propagation.queue[0], vec![3, 8, 13]);
+            assert_eq!(
+                analyzer::Stats::from_level(&puzzle, level::Level::Medium),
+                reference(&puzzle, &tactics)
+            );
+        }
+    }
+
+    #[test]
+    fn queue() {
+        let puzzle = puzzle::Puzzle::from_codex("11c00cd11", 4, 4).unwrap();
+        let tactics = [tactics::Tactics::Row2];
+        let mut propagation = Propagation::new(&puzzle, &tactics);
+
+        assert_eq!(propagation.hints(0), tactics[0].hints(&puzzle));
+        assert!(propagation.queue[0].is_empty());
+
+        propagation.set(2, 0, 0);
+        assert_eq!(propagation.queue[0], vec![0, 6]);
+        assert_eq!(propagation.hints(0), tactics[0].hints(propagation.puzzle()));
+    }
+}
@@ -9,13 +9,18 @@ pub struct Backtrack;
 
 impl Tactic for Backtrack {
     fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint> {
+        // there is nothing to solve if the puzzle is already filled in
+        if puzzle.givens() == puzzle.size() {
+            return Vec::new();
+        }
+
         let solution = solver::solve(puzzle)
             .expect("All puzzles given to the backtrack tactic should be solvable.");
 
         let mut hints = Vec::new();
         for y in 0..puzzle.height() {
             for x in 0..puzzle.width() {
-                if puzzle[y][x].is_none() {
+                if puzzle[y][x].is_none() && !puzzle.blocked(x, y) {
                     hints.push(hint::Hint::new(x, y, solution[y][x].unwrap()));
                 }
             }
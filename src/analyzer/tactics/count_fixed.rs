@@ -1,67 +1,74 @@
-use crate::analyzer::tactics::{hint, Tactic};
+use crate::analyzer::tactics::{hint, line, Tactic};
 use crate::puzzle;
 
 /// Tactic applied when a line has all the 0's or 1's already, fill the rest
-/// with the other symbol.
+/// with the other symbol. With more symbols the rest is filled once the line
+/// has all of every symbol but one. When a clue gives the number of 1's in the
+/// line, the line has all its 1's once it has that many and all its 0's once it
+/// has as many as the other cells.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CountFixed;
 
 impl Tactic for CountFixed {
     fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint> {
-        let mut hints = Vec::new();
+        let (rows, columns) = line::all(self, puzzle);
+        return line::concat(puzzle, &rows, &columns);
+    }
+}
 
-        // check horizontal lines
-        for y in 0..puzzle.height() {
-            let mut count: [usize; 2] = [0, 0];
+impl line::LineTactic for CountFixed {
+    fn line_hints(&self, puzzle: &puzzle::Puzzle, line: line::Line) -> Vec<hint::Hint> {
+        let mut hints = Vec::new();
 
-            // count the 0's and 1's
-            for x in 0..puzzle.width() {
-                if let Some(symbol) = puzzle[y][x] {
-                    count[symbol as usize] += 1;
-                }
+        // every segment is balanced on its own
+        for segment in line.segments(puzzle) {
+            let values = segment.values(puzzle);
+            let most = *segment.rules.count(values.len()).end();
+            let most = vec![most; segment.rules.symbols as usize];
+            for (i, v) in fill(&values, &most) {
+                hints.push(segment.hint(i, v));
             }
+        }
 
-            // fill
-            for symbol in [0, 1] {
-                if count[symbol] == puzzle.width() / 2 && count[symbol ^ 1] != puzzle.width() / 2 {
-                    for x in 0..puzzle.width() {
-                        if puzzle[y][x].is_none() {
-                            hints.push(hint::Hint::new(x, y, (symbol ^ 1).try_into().unwrap()));
-                        }
-                    }
-                }
+        // the clue counts the 1's in all the cells which are not blocked
+        if let Some(clue) = line.clue(puzzle) {
+            let blocked = line.blocked(puzzle);
+            let (cells, values): (Vec<_>, Vec<_>) = line
+                .values(puzzle)
+                .into_iter()
+                .enumerate()
+                .filter(|&(i, _)| !blocked[i])
+                .unzip();
+            for (k, v) in fill(&values, &[values.len().saturating_sub(clue), clue]) {
+                hints.push(line.hint(cells[k], v));
             }
         }
+        return hints;
+    }
+}
 
-        // check vertical lines
-        for x in 0..puzzle.width() {
-            let mut count: [usize; 2] = [0, 0];
-
-            // count the 0's and 1's
-            for y in 0..puzzle.height() {
-                if let Some(symbol) = puzzle[y][x] {
-                    count[symbol as usize] += 1;
-                }
-            }
+/// Return the empty cells which get the remaining symbol, as all the other
+/// symbols appear the most times they can already.
+fn fill(values: &[Option<u8>], most: &[usize]) -> Vec<(usize, u8)> {
+    // count every symbol
+    let mut count = vec![0; most.len()];
+    for symbol in values.iter().flatten() {
+        count[*symbol as usize] += 1;
+    }
 
-            // fill
-            for symbol in [0, 1] {
-                if count[symbol] == puzzle.height() / 2 && count[symbol ^ 1] != puzzle.height() / 2
-                {
-                    for y in 0..puzzle.height() {
-                        if puzzle[y][x].is_none() {
-                            let hint = hint::Hint::new(x, y, (symbol ^ 1).try_into().unwrap());
-                            if !hints.contains(&hint) {
-                                hints.push(hint);
-                            }
-                        }
-                    }
+    // fill
+    let mut filled = Vec::new();
+    for symbol in 0..most.len() {
+        let others = (0..most.len()).filter(|&other| other != symbol);
+        if count[symbol] != most[symbol] && others.into_iter().all(|o| count[o] == most[o]) {
+            for (i, value) in values.iter().enumerate() {
+                if value.is_none() {
+                    filled.push((i, symbol.try_into().unwrap()));
                 }
             }
         }
-
-        return hints;
     }
+    return filled;
 }
 
 #[cfg(test)]
@@ -141,4 +148,52 @@ mod tests {
                 ]
         );
     }
+
+    #[test]
+    fn relaxed() {
+        // a line of five can have at most three ones or zeros
+        let puzzle =
+            puzzle::Puzzle::from_codex_with("1a11u", 5, 5, puzzle::rules::Rules::relaxed())
+                .unwrap();
+        assert_eq!(
+            CountFixed.hints(&puzzle),
+            vec![hint::Hint::new(1, 0, 0), hint::Hint::new(4, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn segments() {
+        // every segment between blocked cells is balanced on its own
+        let puzzle = puzzle::Puzzle::from_codex("1a_czd", 6, 6).unwrap();
+        assert_eq!(CountFixed.hints(&puzzle), vec![hint::Hint::new(1, 0, 0)]);
+    }
+
+    #[test]
+    fn trinairo() {
+        // the line has all its 0's and 1's, so the rest are 2's
+        let rules = puzzle::rules::Rules::trinairo();
+        let puzzle = puzzle::Puzzle::from_codex_with("0011bzd", 6, 6, rules).unwrap();
+        assert_eq!(
+            CountFixed.hints(&puzzle),
+            vec![hint::Hint::new(4, 0, 2), hint::Hint::new(5, 0, 2)]
+        );
+    }
+
+    #[test]
+    fn clues() {
+        // the clues give the number of ones instead of the balance
+        let rules = puzzle::rules::Rules::clued();
+        let puzzle = puzzle::Puzzle::from_codex_with("1c0k::1,3,,,,,,", 4, 4, rules).unwrap();
+        assert_eq!(
+            CountFixed.hints(&puzzle),
+            vec![
+                hint::Hint::new(1, 0, 0),
+                hint::Hint::new(2, 0, 0),
+                hint::Hint::new(3, 0, 0),
+                hint::Hint::new(1, 1, 1),
+                hint::Hint::new(2, 1, 1),
+                hint::Hint::new(3, 1, 1),
+            ]
+        );
+    }
 }
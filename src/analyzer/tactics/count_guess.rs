@@ -1,107 +1,33 @@
-use crate::analyzer::tactics::{hint, Tactic};
+use crate::analyzer::tactics::{hint, line, Tactic};
 use crate::puzzle;
 
-/// Advanced tactic which uses backtracking on one line. Guess a value, if no
-/// valid line can follow we know the guessed value should be the opposite.
+/// Advanced tactic which looks at all the valid ways to fill in one line. Guess a
+/// value, if no valid line can follow we know the guessed value should be the
+/// opposite.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CountGuess;
 
 impl Tactic for CountGuess {
     fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint> {
-        let mut hints = Vec::new();
-
-        for y in 0..puzzle.height() {
-            'next_cell: for x in 0..puzzle.width() {
-                if puzzle[y][x].is_none() {
-                    for guess in [0, 1] {
-                        // create the horizontal line
-                        let mut line = puzzle[y].clone();
-                        line[x] = Some(guess);
-
-                        // check horizontal line
-                        if !backtrack(&mut line, None) {
-                            hints.push(hint::Hint::new(x, y, guess ^ 1));
-                            continue 'next_cell;
-                        }
-
-                        // create the vertical line
-                        let mut line: Vec<_> = (0..puzzle.height()).map(|y| puzzle[y][x]).collect();
-                        line[y] = Some(guess);
-
-                        // check vertical line
-                        if !backtrack(&mut line, None) {
-                            hints.push(hint::Hint::new(x, y, guess ^ 1));
-                            continue 'next_cell;
-                        }
-                    }
-                }
-            }
-        }
-        return hints;
-    }
-}
-
-/// Backtrack on the given line and return whether a valid state exists or not.
-fn backtrack(line: &mut Vec<Option<u8>>, last: Option<usize>) -> bool {
-    if last.is_some() && !valid(line, last.unwrap()) {
-        return false; // the line is invalid
-    }
-
-    if line.iter().all(|v| v.is_some()) {
-        return true; // the line is valid and completely filled
-    }
-
-    for i in 0..line.len() {
-        if line[i].is_none() {
-            for guess in [0, 1] {
-                line[i] = Some(guess);
-
-                if backtrack(line, Some(i)) {
-                    return true;
-                }
-
-                line[i] = None;
-            }
-        }
+        let (rows, columns) = line::all(self, puzzle);
+        return line::merge(puzzle, &rows, &columns);
     }
-
-    return false; // no valid state was found
 }
 
-/// Check whether a given row is still valid given the last changed index.
-fn valid(line: &Vec<Option<u8>>, last: usize) -> bool {
-    // check [. . last]
-    if last > 1 && line[last] == line[last - 1] && line[last] == line[last - 2] {
-        return false;
-    }
-
-    // check [last . .]
-    if last < line.len() - 2 && line[last] == line[last + 1] && line[last] == line[last + 2] {
-        return false;
-    }
-
-    // check [. last .]
-    if last > 0
-        && last < line.len() - 1
-        && line[last] == line[last - 1]
-        && line[last] == line[last + 1]
-    {
-        return false;
-    }
-
-    // check counts
-    let mut count: [usize; 2] = [0, 0];
-    for i in 0..line.len() {
-        if let Some(symbol) = line[i] {
-            count[symbol as usize] += 1;
-        }
-    }
-    if count[0] > line.len() / 2 || count[1] > line.len() / 2 {
-        return false;
+impl line::LineTactic for CountGuess {
+    fn line_hints(&self, puzzle: &puzzle::Puzzle, line: line::Line) -> Vec<hint::Hint> {
+        line::forced(
+            &line.values(puzzle),
+            &line.blocked(puzzle),
+            &line.markers(puzzle),
+            line.clue(puzzle),
+            &[],
+            &line.rules(puzzle),
+        )
+        .into_iter()
+        .map(|(i, v)| line.hint(i, v))
+        .collect()
     }
-
-    // everything is valid
-    return true;
 }
 
 #[cfg(test)]
@@ -156,4 +82,10 @@ mod tests {
                 ]
         )
     }
+
+    #[test]
+    fn large() {
+        let puzzle = puzzle::Puzzle::new(20, 20).unwrap();
+        assert!(CountGuess.hints(&puzzle).is_empty());
+    }
 }
@@ -1,16 +1,12 @@
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Hint {
     pub x: usize,
     pub y: usize,
-    pub v: u8, // value of 0 or 1
+    pub v: u8, // value of one of the symbols, 0 or 1 by default
 }
 
 impl Hint {
     pub fn new(x: usize, y: usize, v: u8) -> Self {
-        Self {
-            x,
-            y,
-            v: if v == 0 { 0 } else { 1 },
-        }
+        Self { x, y, v }
     }
 }
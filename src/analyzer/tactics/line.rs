@@ -0,0 +1,902 @@
+use crate::analyzer::tactics::hint;
+use crate::puzzle::{self, line::between, marker, rules};
+use std::ops;
+
+pub use crate::puzzle::line::{diagonals, through, Line, Segment};
+
+impl Line {
+    /// Create a hint for the i-th cell on this line.
+    pub fn hint(&self, i: usize, v: u8) -> hint::Hint {
+        let (x, y) = self.position(i);
+        hint::Hint::new(x, y, v)
+    }
+}
+
+impl Segment {
+    /// Create a hint for the i-th cell in this segment.
+    pub fn hint(&self, i: usize, v: u8) -> hint::Hint {
+        self.line.hint(self.cells[i], v)
+    }
+}
+
+/// Tactics which can be applied to one line at a time.
+pub trait LineTactic {
+    /// Give the hints which follow from looking at the given line.
+    fn line_hints(&self, puzzle: &puzzle::Puzzle, line: Line) -> Vec<hint::Hint>;
+}
+
+/// Tactics which can be applied to one segment of a line at a time, which are
+/// applied to a line by going through all its segments.
+pub trait SegmentTactic {
+    /// Give the hints which follow from looking at the given segment.
+    fn segment_hints(&self, puzzle: &puzzle::Puzzle, segment: &Segment) -> Vec<hint::Hint>;
+}
+
+impl<T: SegmentTactic> LineTactic for T {
+    fn line_hints(&self, puzzle: &puzzle::Puzzle, line: Line) -> Vec<hint::Hint> {
+        line.segments(puzzle)
+            .iter()
+            .flat_map(|segment| self.segment_hints(puzzle, segment))
+            .collect()
+    }
+}
+
+/// Apply the tactic to all the horizontal and all the vertical lines, where the
+/// hints of the diagonals which follow rules come after the vertical lines.
+pub fn all<T: LineTactic>(
+    tactic: &T,
+    puzzle: &puzzle::Puzzle,
+) -> (Vec<Vec<hint::Hint>>, Vec<Vec<hint::Hint>>) {
+    let mut rows: Vec<_> = puzzle::line::all(puzzle)
+        .into_iter()
+        .map(|line| tactic.line_hints(puzzle, line))
+        .collect();
+    let columns = rows.split_off(puzzle.height());
+    return (rows, columns);
+}
+
+/// Return the line as a number in the base of the number of symbols, where
+/// digit i holds the value of cell i and empty cells count as a 0. With two
+/// symbols this is the bitmask of the ones. The rules make sure the lines are
+/// short enough for this number to fit.
+pub fn pattern(line: &[Option<u8>], symbols: u8) -> u64 {
+    line.iter().rev().fold(0, |pattern, cell| {
+        pattern * symbols as u64 + cell.unwrap_or(0) as u64
+    })
+}
+
+/// Return the value of cell i of a line stored as a number by `pattern`.
+pub fn digit(pattern: u64, i: usize, symbols: u8) -> u8 {
+    (pattern / (symbols as u64).pow(i as u32) % symbols as u64) as u8
+}
+
+/// The number of valid ways to fill in a line, in total and with each of the
+/// values in each of the cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counts {
+    pub total: u64,
+    pub cells: Vec<Vec<u64>>,
+}
+
+/// Count the ways to fill in the given line which are valid under the rules,
+/// agree with the markers between its cells and have the number of ones of the
+/// clue if given, leaving out the completions which are in the exclusion set.
+/// The blocked cells split the line into segments, which are filled in on their
+/// own.
+pub fn count(
+    line: &[Option<u8>],
+    blocked: &[bool],
+    markers: &[Option<marker::Marker>],
+    clue: Option<usize>,
+    exclude: &[u64],
+    rules: &rules::Rules,
+) -> Counts {
+    let width = line.len();
+    let symbols = rules.symbols as usize;
+    let segments: Vec<_> = rules
+        .segments(blocked)
+        .into_iter()
+        .map(|cells| {
+            let values: Vec<_> = cells.iter().map(|&i| line[i]).collect();
+            let markers = between(&cells, markers);
+            let rules = rules.segment(&cells, blocked);
+            (cells, values, markers, rules)
+        })
+        .collect();
+    let mut counts = Counts {
+        total: 1,
+        cells: vec![vec![0; symbols]; width],
+    };
+
+    match clue {
+        None => {
+            // the ways to fill in a segment combine with the ways to fill in the others
+            for (cells, values, markers, rules) in &segments {
+                let part = segment(values, markers, rules, rules.count(cells.len()));
+                for cell in counts.cells.iter_mut().flatten() {
+                    *cell *= part.total;
+                }
+                for (k, &i) in cells.iter().enumerate() {
+                    for v in 0..symbols {
+                        counts.cells[i][v] = part.cells[k][v] * counts.total;
+                    }
+                }
+                counts.total *= part.total;
+            }
+        }
+        Some(clue) => {
+            // the segments together have the number of ones of the clue, so every
+            // segment is counted apart for every number of ones it can have
+            let parts: Vec<Vec<Counts>> = segments
+                .iter()
+                .map(|(cells, values, markers, rules)| {
+                    let range = rules.count(cells.len());
+                    (0..=cells.len())
+                        .map(|ones| match range.contains(&ones) {
+                            true => segment(values, markers, rules, ones..=ones),
+                            false => Counts {
+                                total: 0,
+                                cells: vec![vec![0; symbols]; cells.len()],
+                            },
+                        })
+                        .collect()
+                })
+                .collect();
+
+            // the ways to fill in the segments before and after every segment,
+            // for every number of ones in them
+            let convolve = |ways: &Vec<u64>, part: &Vec<Counts>| {
+                let mut next = vec![0; ways.len() + part.len() - 1];
+                for (a, w) in ways.iter().enumerate() {
+                    for (b, p) in part.iter().enumerate() {
+                        next[a + b] += w * p.total;
+                    }
+                }
+                next
+            };
+            let mut before = vec![vec![1]];
+            for part in &parts {
+                before.push(convolve(before.last().unwrap(), part));
+            }
+            let mut after = vec![vec![1]];
+            for part in parts.iter().rev() {
+                after.push(convolve(after.last().unwrap(), part));
+            }
+            after.reverse();
+
+            counts.total = before[parts.len()].get(clue).copied().unwrap_or(0);
+            for (s, ((cells, ..), part)) in segments.iter().zip(&parts).enumerate() {
+                for (ones, counted) in part.iter().enumerate().take(clue + 1) {
+                    let others: u64 = (0..before[s].len().min(clue - ones + 1))
+                        .map(|j| before[s][j] * after[s + 1].get(clue - ones - j).unwrap_or(&0))
+                        .sum();
+                    for (k, &i) in cells.iter().enumerate() {
+                        for v in 0..symbols {
+                            counts.cells[i][v] += counted.cells[k][v] * others;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // leave out the excluded lines which are valid completions
+    let mut excluded = Vec::new();
+    for &pattern in exclude {
+        let known =
+            (0..width).all(|i| line[i].is_none_or(|v| digit(pattern, i, rules.symbols) == v));
+        if known && valid(pattern, blocked, markers, clue, rules) && !excluded.contains(&pattern) {
+            excluded.push(pattern);
+            counts.total -= 1;
+            for i in (0..width).filter(|&i| !blocked[i]) {
+                counts.cells[i][digit(pattern, i, rules.symbols) as usize] -= 1;
+            }
+        }
+    }
+    return counts;
+}
+
+/// Count the ways to fill in the given segment which are valid under the rules,
+/// agree with the markers between its cells and have a number of ones in the
+/// given range.
+fn segment(
+    line: &[Option<u8>],
+    markers: &[Option<marker::Marker>],
+    rules: &rules::Rules,
+    ones: ops::RangeInclusive<usize>,
+) -> Counts {
+    let width = line.len();
+    let symbols = rules.symbols;
+    let mut counts = Counts {
+        total: 0,
+        cells: vec![vec![0; symbols as usize]; width],
+    };
+
+    match (rules.wrap, rules.run) {
+        (true, Some(longest)) => {
+            // the run at the end continues with the run at the start, so the
+            // lines are counted apart for every value and length of the first run
+            // and the value after it
+            for first in 0..symbols {
+                for length in 1..=longest.min(width - 1) {
+                    for second in (0..symbols).filter(|&second| second != first) {
+                        let mut start = line.to_vec();
+                        start[..length].fill(Some(first));
+                        start[length] = Some(second);
+                        if start.iter().zip(line).any(|(s, l)| l.is_some() && s != l) {
+                            continue;
+                        }
+
+                        let part = paths(&start, markers, rules, ones.clone(), |last, run| {
+                            last != first as usize || run + length <= longest
+                        });
+                        counts.total += part.total;
+                        for (cell, counted) in counts.cells.iter_mut().zip(part.cells) {
+                            for (count, counted) in cell.iter_mut().zip(counted) {
+                                *count += counted;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        _ => counts = paths(line, markers, rules, ones, |_, _| true),
+    }
+    return counts;
+}
+
+/// Count the ways to fill in the given line which are valid under the rules,
+/// without wrapping around, and which end in a run of a value and length which
+/// is accepted by the given function. Instead of going through all the valid
+/// lines this counts the ways to fill in the cells before and after every cell,
+/// only keeping track of the number of times every symbol appears and the run
+/// of equal values at the end. The number of every symbol other than 0 has to
+/// be in the given range, with two symbols the number of zeros follows from the
+/// number of ones and otherwise it has to be in the same range.
+fn paths(
+    line: &[Option<u8>],
+    markers: &[Option<marker::Marker>],
+    rules: &rules::Rules,
+    ones: ops::RangeInclusive<usize>,
+    end: impl Fn(usize, usize) -> bool,
+) -> Counts {
+    let width = line.len();
+    let symbols = rules.symbols as usize;
+    let (low, high) = ones.into_inner();
+    let zeros = match symbols {
+        2 => width.saturating_sub(high)..=width - low.min(width),
+        _ => low..=high,
+    };
+
+    // the numbers of the symbols other than 0 are stored as the digits of one
+    // number, which are only tracked when the range limits them
+    let tracked = low > 0 || high < width;
+    let base = if tracked { high + 1 } else { 1 };
+    let numbers = base.pow(symbols as u32 - 1);
+    let number = |numbers: usize, v: usize| numbers / base.pow(v as u32 - 1) % base;
+    let sums: Vec<usize> = (0..numbers)
+        .map(|n| (1..symbols).map(|v| number(n, v)).sum())
+        .collect();
+    let (sums, most) = (&sums, *zeros.end());
+
+    // a state is the numbers of the symbols, the last value and the length of
+    // its run, where a run is only tracked up to the longest run the rules allow
+    let longest = rules.run.unwrap_or(1);
+    let states = numbers * symbols * (longest + 1);
+    let index = |n: usize, last: usize, run: usize| (n * symbols + last) * (longest + 1) + run;
+    let reachable = |i: usize| {
+        let (lasts, runs) = match i {
+            0 => (0..1, 0..1),
+            _ => (0..symbols, 1..longest + 1),
+        };
+        (0..numbers)
+            .filter(move |&n| sums[n] <= i && (!tracked || i - sums[n] <= most))
+            .flat_map(move |n| {
+                let runs = runs.clone();
+                lasts
+                    .clone()
+                    .flat_map(move |last| runs.clone().map(move |run| (n, last, run)))
+            })
+    };
+    let next = |i: usize, (n, last, run): (usize, usize, usize), v: usize| {
+        let run = if run > 0 && last == v { run + 1 } else { 1 };
+        let n = match v {
+            0 => Some(n),
+            _ if !tracked => Some(n),
+            _ => (number(n, v) < high).then(|| n + base.pow(v as u32 - 1)),
+        }?;
+        let marker = if i > 0 {
+            markers.get(i - 1).copied().flatten()
+        } else {
+            None
+        };
+        let allowed = line[i].is_none_or(|known| known as usize == v)
+            && marker.is_none_or(|marker| marker.allows(last as u8, v as u8))
+            && rules.run.is_none_or(|longest| run <= longest)
+            && (!tracked || i + 1 - sums[n] <= most);
+        allowed.then(|| index(n, v, run.min(longest)))
+    };
+    let complete = |n: usize| {
+        !tracked
+            || ((1..symbols).all(|v| number(n, v) >= low) && zeros.contains(&(width - sums[n])))
+    };
+
+    // the ways to fill in the first i cells ending in every state
+    let mut before = vec![0u64; (width + 1) * states];
+    before[index(0, 0, 0)] = 1;
+    for i in 0..width {
+        for state in reachable(i) {
+            let ways = before[i * states + index(state.0, state.1, state.2)];
+            for v in 0..symbols {
+                if let Some(t) = next(i, state, v) {
+                    before[(i + 1) * states + t] += ways;
+                }
+            }
+        }
+    }
+
+    // the ways to fill in the cells from i onwards starting from every state
+    let mut after = vec![0u64; (width + 1) * states];
+    for (n, last, run) in reachable(width) {
+        after[width * states + index(n, last, run)] = (complete(n) && end(last, run)) as u64;
+    }
+    let mut counts = Counts {
+        total: 0,
+        cells: vec![vec![0; symbols]; width],
+    };
+    for i in (0..width).rev() {
+        for state in reachable(i) {
+            let s = index(state.0, state.1, state.2);
+            for v in 0..symbols {
+                if let Some(t) = next(i, state, v) {
+                    let ways = after[(i + 1) * states + t];
+                    after[i * states + s] += ways;
+                    counts.cells[i][v] += before[i * states + s] * ways;
+                }
+            }
+        }
+    }
+    counts.total = after[index(0, 0, 0)];
+    return counts;
+}
+
+/// Check whether all the segments of the completely filled line are valid, and
+/// whether the line has the number of ones of the clue if given.
+fn valid(
+    pattern: u64,
+    blocked: &[bool],
+    markers: &[Option<marker::Marker>],
+    clue: Option<usize>,
+    rules: &rules::Rules,
+) -> bool {
+    let values: Vec<_> = (0..blocked.len())
+        .map(|i| digit(pattern, i, rules.symbols))
+        .collect();
+    let ones = (0..blocked.len())
+        .filter(|&i| !blocked[i] && values[i] == 1)
+        .count();
+    if clue.is_some_and(|clue| clue != ones) {
+        return false;
+    }
+    return rules.segments(blocked).iter().all(|cells| {
+        let values: Vec<_> = cells.iter().map(|&i| values[i]).collect();
+        valid_segment(
+            &values,
+            &between(cells, markers),
+            &rules.segment(cells, blocked),
+        )
+    });
+}
+
+/// Check whether the completely filled segment is balanced and does not have
+/// more consecutive equal values than the rules allow, also around the end when
+/// the segment wraps, and whether it agrees with the markers.
+fn valid_segment(values: &[u8], markers: &[Option<marker::Marker>], rules: &rules::Rules) -> bool {
+    let width = values.len();
+    let runs = match rules.run {
+        Some(longest) => {
+            let starts = match rules.wrap {
+                true => width,
+                false => width.saturating_sub(longest),
+            };
+            (0..starts).all(|i| (i..=i + longest).any(|j| values[j % width] != values[i]))
+        }
+        None => true,
+    };
+    return markers
+        .iter()
+        .enumerate()
+        .all(|(i, marker)| marker.is_none_or(|marker| marker.allows(values[i], values[i + 1])))
+        && (0..rules.symbols).all(|v| {
+            let count = values.iter().filter(|&&value| value == v).count();
+            rules.count(width).contains(&count)
+        })
+        && runs;
+}
+
+/// Return all the empty cells in the line which can only have one value, given
+/// the completions of the line. When a cell can not have any value at all the
+/// line is invalid, in which case the cell is said to be a 1.
+pub fn forced(
+    line: &[Option<u8>],
+    blocked: &[bool],
+    markers: &[Option<marker::Marker>],
+    clue: Option<usize>,
+    exclude: &[u64],
+    rules: &rules::Rules,
+) -> Vec<(usize, u8)> {
+    let counts = count(line, blocked, markers, clue, exclude, rules);
+
+    let mut forced = Vec::new();
+    for (i, cell) in line.iter().enumerate() {
+        if cell.is_none() && !blocked[i] {
+            let mut possible = (0..rules.symbols).filter(|&v| counts.cells[i][v as usize] > 0);
+            match (possible.next(), possible.next()) {
+                (None, _) => forced.push((i, 1)),
+                (Some(v), None) => forced.push((i, v)),
+                _ => (),
+            }
+        }
+    }
+    return forced;
+}
+
+/// Combine the hints of all the horizontal and vertical lines, first the hints
+/// of the horizontal lines and then those of the vertical lines, where every
+/// hint is only given once.
+pub fn concat(
+    puzzle: &puzzle::Puzzle,
+    rows: &[Vec<hint::Hint>],
+    columns: &[Vec<hint::Hint>],
+) -> Vec<hint::Hint> {
+    let mut found = vec![vec![Vec::new(); puzzle.width()]; puzzle.height()];
+
+    let mut hints = Vec::new();
+    for hint in rows.iter().chain(columns.iter()).flatten() {
+        if !found[hint.y][hint.x].contains(&hint.v) {
+            found[hint.y][hint.x].push(hint.v);
+            hints.push(*hint);
+        }
+    }
+    return hints;
+}
+
+/// Combine the hints of all the horizontal and vertical lines into one hint per
+/// cell, ordered by position. When the lines disagree a 1 is preferred.
+pub fn merge(
+    puzzle: &puzzle::Puzzle,
+    rows: &[Vec<hint::Hint>],
+    columns: &[Vec<hint::Hint>],
+) -> Vec<hint::Hint> {
+    let mut values = vec![vec![None; puzzle.width()]; puzzle.height()];
+
+    for hint in rows.iter().chain(columns.iter()).flatten() {
+        values[hint.y][hint.x] = values[hint.y][hint.x].max(Some(hint.v));
+    }
+
+    let mut hints = Vec::new();
+    for (y, row) in values.iter().enumerate() {
+        for (x, value) in row.iter().enumerate() {
+            if let Some(v) = value {
+                hints.push(hint::Hint::new(x, y, *v));
+            }
+        }
+    }
+    return hints;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::tactics::patterns;
+    use itertools::Itertools;
+
+    #[test]
+    fn lines() {
+        let puzzle = puzzle::Puzzle::from_codex("1a1j0a0", 4, 4).unwrap();
+        assert_eq!(
+            Line::Row(0).values(&puzzle),
+            vec![Some(1), None, Some(1), None]
+        );
+        assert_eq!(
+            Line::Column(1).values(&puzzle),
+            vec![None, None, None, Some(0)]
+        );
+        assert_eq!(Line::Column(3).hint(1, 0), hint::Hint::new(3, 1, 0));
+    }
+
+    #[test]
+    fn combine() {
+        let rows = vec![
+            vec![hint::Hint::new(1, 0, 0), hint::Hint::new(1, 0, 0)],
+            vec![],
+        ];
+        let columns = vec![
+            vec![],
+            vec![hint::Hint::new(1, 0, 0), hint::Hint::new(1, 1, 1)],
+        ];
+        let puzzle = puzzle::Puzzle::new(4, 4).unwrap();
+        assert_eq!(
+            concat(&puzzle, &rows, &columns),
+            vec![hint::Hint::new(1, 0, 0), hint::Hint::new(1, 1, 1)]
+        );
+
+        let rows = vec![
+            vec![hint::Hint::new(1, 1, 0)],
+            vec![hint::Hint::new(0, 1, 0)],
+        ];
+        let columns = vec![
+            vec![hint::Hint::new(1, 1, 1)],
+            vec![hint::Hint::new(0, 0, 1)],
+        ];
+        assert_eq!(
+            merge(&puzzle, &rows, &columns),
+            vec![
+                hint::Hint::new(0, 0, 1),
+                hint::Hint::new(0, 1, 0),
+                hint::Hint::new(1, 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts() {
+        let line = [None, Some(1), None, None];
+        let counts = count(&line, &[false; 4], &[], None, &[], &rules::Rules::default());
+        assert_eq!(counts.total, 3);
+        assert_eq!(counts.cells, vec![[2, 1], [0, 3], [2, 1], [2, 1]]);
+
+        let counts = count(
+            &line,
+            &[false; 4],
+            &[],
+            None,
+            &[0b0110, 0b0110, 0b0111],
+            &rules::Rules::default(),
+        );
+        assert_eq!(counts.total, 2);
+        assert_eq!(counts.cells, vec![[1, 1], [0, 2], [2, 0], [1, 1]]);
+
+        // the counts agree with going through all the completions
+        let line = [
+            None,
+            Some(0),
+            None,
+            None,
+            None,
+            None,
+            Some(1),
+            None,
+            None,
+            None,
+        ];
+        let (mask, value) = patterns::known(&line);
+        let completions: Vec<_> = patterns::patterns(line.len())
+            .iter()
+            .copied()
+            .filter(|&pattern| pattern & mask == value && pattern != 0b1001100110)
+            .collect();
+        let counts = count(
+            &line,
+            &[false; 10],
+            &[],
+            None,
+            &[0b1001100110],
+            &rules::Rules::default(),
+        );
+        assert_eq!(counts.total, completions.len() as u64);
+        for i in 0..line.len() {
+            let ones = completions.iter().filter(|&c| (c >> i) & 1 == 1).count();
+            assert_eq!(counts.cells[i][1], ones as u64);
+        }
+
+        assert_eq!(
+            count(
+                &[None; 30],
+                &[false; 30],
+                &[],
+                None,
+                &[],
+                &rules::Rules::default()
+            )
+            .total,
+            860142
+        );
+        assert_eq!(
+            count(
+                &[Some(1), Some(1), Some(1), None],
+                &[false; 4],
+                &[],
+                None,
+                &[],
+                &rules::Rules::default()
+            )
+            .total,
+            0
+        );
+
+        // lines of odd length can have one more of either symbol
+        let counts = count(
+            &[None; 5],
+            &[false; 5],
+            &[],
+            None,
+            &[],
+            &rules::Rules::relaxed(),
+        );
+        assert_eq!(counts.total, 14);
+        let counts = count(
+            &[Some(0), Some(0), None, None, None],
+            &[false; 5],
+            &[],
+            None,
+            &[],
+            &rules::Rules::relaxed(),
+        );
+        assert_eq!(counts.total, 2);
+        assert_eq!(counts.cells[2], [0, 2]);
+
+        // without a limit any number of equal values can follow each other
+        let rules = rules::Rules {
+            run: None,
+            ..Default::default()
+        };
+        assert_eq!(
+            count(&[None; 6], &[false; 6], &[], None, &[], &rules).total,
+            20
+        );
+        let counts = count(
+            &[Some(1), Some(1), None, None, None, None],
+            &[false; 6],
+            &[],
+            None,
+            &[0b001011],
+            &rules,
+        );
+        assert_eq!(counts.total, 3);
+        assert_eq!(counts.cells[2], [2, 1]);
+        let rules = rules::Rules {
+            balance: rules::Balance::Free,
+            ..rules
+        };
+        assert_eq!(
+            count(&[None; 6], &[false; 6], &[], None, &[], &rules).total,
+            64
+        );
+
+        // the markers between the cells have to hold, also for excluded lines
+        let markers = [Some(marker::Marker::Equal), None, None];
+        let counts = count(
+            &[None; 4],
+            &[false; 4],
+            &markers,
+            None,
+            &[0b0101],
+            &rules::Rules::default(),
+        );
+        assert_eq!(counts.total, 2);
+        assert_eq!(counts.cells[1], [1, 1]);
+        let markers = [None, Some(marker::Marker::Opposite), None];
+        let counts = count(
+            &[None; 4],
+            &[false; 4],
+            &markers,
+            None,
+            &[0b1100],
+            &rules::Rules::default(),
+        );
+        assert_eq!(counts.total, 3);
+    }
+
+    #[test]
+    fn runs() {
+        // the counts agree with going through all the lines, also when the runs
+        // continue from the end of the line at its start
+        let line = [None, Some(0), None, None, None, None, Some(1), None];
+        let n = line.len();
+        for (longest, wrap) in (1..=4).cartesian_product([false, true]) {
+            let rules = rules::Rules {
+                run: Some(longest),
+                wrap,
+                ..Default::default()
+            };
+            let lines: Vec<u64> = (0..1u64 << n)
+                .filter(|&pattern| {
+                    let bits: Vec<_> = (0..n).map(|i| (pattern >> i) & 1).collect();
+                    let starts = if wrap { n } else { n - longest };
+                    let runs =
+                        (0..starts).all(|i| (i..=i + longest).any(|j| bits[j % n] != bits[i]));
+                    let known = (0..n).all(|i| line[i].is_none_or(|v| v as u64 == bits[i]));
+                    bits.iter().sum::<u64>() == 4 && runs && known
+                })
+                .collect();
+
+            let counts = count(&line, &[false; 8], &[], None, &[], &rules);
+            assert_eq!(counts.total, lines.len() as u64);
+            for i in 0..n {
+                let ones = lines.iter().filter(|&l| (l >> i) & 1 == 1).count();
+                assert_eq!(counts.cells[i][1], ones as u64);
+            }
+
+            // the excluded lines are only left out when they are valid
+            let counts = count(&line, &[false; 8], &[], None, &lines, &rules);
+            assert_eq!(counts.total, 0);
+        }
+    }
+
+    #[test]
+    fn segments() {
+        // the blocked cells split the line into segments which are counted apart
+        let line = [None, Some(1), None, None, None, None, None, None];
+        let blocked = [false, false, true, false, false, false, true, false];
+        let rules = rules::Rules::relaxed();
+        let counts = count(&line, &blocked, &[], None, &[], &rules);
+        let parts = [
+            count(&line[..2], &[false; 2], &[], None, &[], &rules),
+            count(&line[3..6], &[false; 3], &[], None, &[], &rules),
+            count(&line[7..], &[false; 1], &[], None, &[], &rules),
+        ];
+        assert_eq!(counts.total, parts.iter().map(|part| part.total).product());
+        assert_eq!(counts.cells[2], [0, 0]);
+        assert_eq!(
+            counts.cells[4][1],
+            parts[1].cells[1][1] * parts[0].total * parts[2].total
+        );
+        assert!(forced(&line, &blocked, &[], None, &[], &rules).contains(&(0, 0)));
+
+        // the segments are not balanced when their length is odd
+        let counts = count(&line, &blocked, &[], None, &[], &rules::Rules::default());
+        assert_eq!(counts.total, 0);
+
+        // the excluded lines only have to be valid in every segment
+        let exclude = [0b00101010, 0b00111010];
+        let counts = count(&line, &blocked, &[], None, &exclude, &rules);
+        assert_eq!(
+            counts.total,
+            parts.iter().map(|part| part.total).product::<u64>() - 1
+        );
+
+        // the segment over the edge of a wrapping line continues at the start
+        let line = [None, None, None, None, None, None, Some(1), Some(1)];
+        let blocked = [false, false, true, false, false, true, false, false];
+        let counts = count(&line, &blocked, &[], None, &[], &rules::Rules::toroidal());
+        assert_eq!(counts.total, 2);
+        assert_eq!(counts.cells[0], [2, 0]);
+        assert_eq!(counts.cells[1], [2, 0]);
+        assert_eq!(
+            count(&line, &blocked, &[], None, &[], &rules::Rules::default()).total,
+            0
+        );
+    }
+
+    #[test]
+    fn clues() {
+        // the counts agree with going through all the lines with the number of
+        // ones of the clue, also when the line has blocked cells
+        let line = [None, Some(0), None, None, None, None, Some(1), None];
+        let n = line.len();
+        let rules = rules::Rules::clued();
+        let blockings = [
+            [false; 8],
+            [false, false, true, false, false, false, false, true],
+        ];
+        for (blocked, clue) in blockings.into_iter().cartesian_product(0..=n) {
+            let lines: Vec<u64> = (0..1u64 << n)
+                .filter(|&pattern| {
+                    let bits: Vec<_> = (0..n)
+                        .map(|i| (!blocked[i]).then_some((pattern >> i) & 1))
+                        .collect();
+                    let runs = bits
+                        .windows(3)
+                        .all(|w| w[0].is_none() || w[0] != w[1] || w[1] != w[2]);
+                    let known = (0..n).all(|i| line[i].is_none_or(|v| bits[i] == Some(v as u64)));
+                    let open = (0..n).all(|i| !blocked[i] || (pattern >> i) & 1 == 0);
+                    bits.iter().flatten().sum::<u64>() == clue as u64 && runs && known && open
+                })
+                .collect();
+
+            let counts = count(&line, &blocked, &[], Some(clue), &[], &rules);
+            assert_eq!(counts.total, lines.len() as u64);
+            for i in 0..n {
+                let ones = lines.iter().filter(|&l| (l >> i) & 1 == 1).count();
+                assert_eq!(counts.cells[i][1], ones as u64);
+            }
+
+            let counts = count(&line, &blocked, &[], Some(clue), &lines, &rules);
+            assert_eq!(counts.total, 0);
+        }
+    }
+
+    #[test]
+    fn symbols() {
+        // the counts agree with going through all the lines of three symbols
+        let line = [None, Some(2), None, None, Some(0), None];
+        let n = line.len();
+        let markers = [None, None, Some(marker::Marker::Opposite), None, None];
+        let lines = |rules: &rules::Rules| -> Vec<u64> {
+            (0..3u64.pow(n as u32))
+                .filter(|&l| {
+                    let values: Vec<_> = (0..n).map(|i| digit(l, i, 3)).collect();
+                    let starts = if rules.wrap { n } else { n - 2 };
+                    let runs = (0..starts).all(|i| (i..=i + 2).any(|j| values[j % n] != values[i]));
+                    let known = (0..n).all(|i| line[i].is_none_or(|v| v == values[i]));
+                    let balanced = (0..3).all(|v| values.iter().filter(|&&w| w == v).count() == 2);
+                    runs && known && balanced && values[2] != values[3]
+                })
+                .collect()
+        };
+        for rules in [
+            rules::Rules::trinairo(),
+            rules::Rules {
+                wrap: true,
+                ..rules::Rules::trinairo()
+            },
+        ] {
+            let lines = lines(&rules);
+            let counts = count(&line, &[false; 6], &markers, None, &[], &rules);
+            assert_eq!(counts.total, lines.len() as u64);
+            for (i, v) in (0..n).cartesian_product(0..3) {
+                let matching = lines.iter().filter(|&&l| digit(l, i, 3) == v).count();
+                assert_eq!(counts.cells[i][v as usize], matching as u64);
+            }
+
+            let counts = count(&line, &[false; 6], &markers, None, &lines[1..], &rules);
+            assert_eq!(counts.total, 1);
+            let last = (0..n).map(|i| (i, digit(lines[0], i, 3)));
+            let forced = forced(&line, &[false; 6], &markers, None, &lines[1..], &rules);
+            assert_eq!(
+                forced,
+                last.filter(|&(i, _)| line[i].is_none()).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(pattern(&[Some(1), None, Some(2)], 3), 19);
+    }
+
+    #[test]
+    fn forced_cells() {
+        assert_eq!(
+            forced(
+                &[None; 4],
+                &[false; 4],
+                &[],
+                None,
+                &[],
+                &rules::Rules::default()
+            ),
+            vec![]
+        );
+        assert_eq!(
+            forced(
+                &[Some(1), None, None, None],
+                &[false; 4],
+                &[],
+                None,
+                &[],
+                &rules::Rules::default()
+            ),
+            vec![]
+        );
+        assert_eq!(
+            forced(
+                &[Some(1), None, None, None],
+                &[false; 4],
+                &[],
+                None,
+                &[0b1001, 0b0101],
+                &rules::Rules::default()
+            ),
+            vec![(1, 1), (2, 0), (3, 0)]
+        );
+        assert_eq!(
+            forced(
+                &[None, None, None, Some(0), Some(0), None],
+                &[false; 6],
+                &[],
+                None,
+                &[],
+                &rules::Rules::default()
+            ),
+            vec![(2, 1), (5, 1)]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::analyzer::tactics::{hint, line, Tactic};
+use crate::puzzle::{self, marker};
+
+/// Tactic applied when seen a marker next to a value, an = gives the other cell
+/// the same value and a × the other value. Two empty cells with an = between
+/// them next to a run which would become too long get the other value. E.g.
+/// 0_=_ becomes 0 1=1. With more than two symbols only an = gives a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Markers;
+
+impl Tactic for Markers {
+    fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint> {
+        let (rows, columns) = line::all(self, puzzle);
+        return line::concat(puzzle, &rows, &columns);
+    }
+}
+
+impl line::SegmentTactic for Markers {
+    fn segment_hints(&self, puzzle: &puzzle::Puzzle, segment: &line::Segment) -> Vec<hint::Hint> {
+        let values = segment.values(puzzle);
+        let mut hints = Vec::new();
+        let mut push = |i: usize, v: u8| hints.push(segment.hint(i, v));
+
+        let binary = segment.rules.symbols == 2;
+        for (i, marker) in segment.markers(puzzle).into_iter().enumerate() {
+            if let Some(marker) = marker {
+                let known = binary || marker == marker::Marker::Equal;
+                match (values[i], values[i + 1]) {
+                    (Some(v), None) if known => push(i + 1, marker.apply(v)),
+                    (None, Some(v)) if known => push(i, marker.apply(v)),
+                    (None, None) if binary && marker == marker::Marker::Equal => {
+                        // the equal cells can not make the runs next to them too long
+                        let longest = segment.rules.run.unwrap_or(usize::MAX);
+                        let (before, after) = match segment.rules.wrap {
+                            true => (
+                                run(values[..i].iter().rev().chain(values[i + 2..].iter().rev())),
+                                run(values[i + 2..].iter().chain(values[..i].iter())),
+                            ),
+                            false => (run(values[..i].iter().rev()), run(values[i + 2..].iter())),
+                        };
+                        for (v, length) in [before, after].into_iter().flatten() {
+                            if length + 2 > longest {
+                                push(i, v ^ 1);
+                                push(i + 1, v ^ 1);
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        return hints;
+    }
+}
+
+/// Return the value and the length of the run of equal values at the start of
+/// the given cells, if the first cell has a value.
+fn run<'a>(mut cells: impl Iterator<Item = &'a Option<u8>>) -> Option<(u8, usize)> {
+    let v = (*cells.next()?)?;
+    return Some((v, 1 + cells.take_while(|&&c| c == Some(v)).count()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn horizontal() {
+        let puzzle = puzzle::Puzzle::from_codex("1e1h1:0b0g1l", 4, 4).unwrap();
+        assert_eq!(
+            Markers.hints(&puzzle),
+            vec![
+                hint::Hint::new(1, 0, 1),
+                hint::Hint::new(0, 1, 0),
+                hint::Hint::new(1, 1, 0),
+                hint::Hint::new(2, 3, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn vertical() {
+        let puzzle = puzzle::Puzzle::from_codex("1j0d:l1b0h", 4, 4).unwrap();
+        assert_eq!(
+            Markers.hints(&puzzle),
+            vec![
+                hint::Hint::new(0, 1, 0),
+                hint::Hint::new(3, 0, 1),
+                hint::Hint::new(3, 1, 1),
+            ]
+        );
+    }
+}
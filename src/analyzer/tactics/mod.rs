@@ -1,19 +1,24 @@
 use crate::puzzle;
+use line::{Line, LineTactic};
 
 mod backtrack;
 mod count_fixed;
 mod count_guess;
+mod markers;
 mod row2;
 mod row3;
 mod uniqueness;
 
 pub mod hint;
+pub mod line;
+pub mod patterns;
 
 /// Different tactics that humans apply to binairo puzzles.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Tactics {
     Row2,
     Row3,
+    Markers,
     CountFixed,
     CountGuess,
     Uniqueness,
@@ -24,11 +29,53 @@ pub trait Tactic {
     fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint>;
 }
 
+impl Tactics {
+    /// Return the hints which follow from looking at the given line, or none if
+    /// the tactic can only be applied to the puzzle as a whole.
+    pub fn line_hints(&self, puzzle: &puzzle::Puzzle, line: Line) -> Option<Vec<hint::Hint>> {
+        match self {
+            Tactics::Row2 => Some(row2::Row2.line_hints(puzzle, line)),
+            Tactics::Row3 => Some(row3::Row3.line_hints(puzzle, line)),
+            Tactics::Markers => Some(markers::Markers.line_hints(puzzle, line)),
+            Tactics::CountFixed => Some(count_fixed::CountFixed.line_hints(puzzle, line)),
+            Tactics::CountGuess => Some(count_guess::CountGuess.line_hints(puzzle, line)),
+            Tactics::Uniqueness => Some(uniqueness::Uniqueness.line_hints(puzzle, line)),
+            Tactics::Backtrack => None,
+        }
+    }
+
+    /// Whether the tactic can be applied to one line at a time.
+    pub fn local(&self) -> bool {
+        *self != Tactics::Backtrack
+    }
+
+    /// Whether the hints on a line also depend on the other full lines in the
+    /// same direction.
+    pub fn parallel(&self) -> bool {
+        *self == Tactics::Uniqueness
+    }
+
+    /// Combine the hints found on all the horizontal and vertical lines into the
+    /// hints for the whole puzzle.
+    pub fn combine(
+        &self,
+        puzzle: &puzzle::Puzzle,
+        rows: &[Vec<hint::Hint>],
+        columns: &[Vec<hint::Hint>],
+    ) -> Vec<hint::Hint> {
+        match self {
+            Tactics::CountGuess | Tactics::Uniqueness => line::merge(puzzle, rows, columns),
+            _ => line::concat(puzzle, rows, columns),
+        }
+    }
+}
+
 impl Tactic for Tactics {
     fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint> {
         match self {
             Tactics::Row2 => row2::Row2.hints(puzzle),
             Tactics::Row3 => row3::Row3.hints(puzzle),
+            Tactics::Markers => markers::Markers.hints(puzzle),
             Tactics::CountFixed => count_fixed::CountFixed.hints(puzzle),
             Tactics::CountGuess => count_guess::CountGuess.hints(puzzle),
             Tactics::Uniqueness => uniqueness::Uniqueness.hints(puzzle),
@@ -40,11 +87,17 @@ impl Tactic for Tactics {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        analyzer::{self, level},
+        solver,
+    };
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn tactic_order() {
         assert!(Tactics::Row2 < Tactics::Row3);
-        assert!(Tactics::Row3 < Tactics::CountFixed);
+        assert!(Tactics::Row3 < Tactics::Markers);
+        assert!(Tactics::Markers < Tactics::CountFixed);
         assert!(Tactics::CountFixed < Tactics::CountGuess);
         assert!(Tactics::CountGuess < Tactics::Uniqueness);
         assert!(Tactics::Uniqueness < Tactics::Backtrack);
@@ -84,4 +137,184 @@ mod tests {
         let tactic = Tactics::Row3;
         tactic_run(tactic);
     }
+
+    /// The line backtracking used by the advanced tactics before the valid line
+    /// patterns were precomputed, the new tactics should give the same hints.
+    fn reference(puzzle: &puzzle::Puzzle, unique: bool) -> Vec<hint::Hint> {
+        fn valid(line: &Vec<Option<u8>>, last: usize) -> bool {
+            let n = line.len();
+            if last > 1 && line[last] == line[last - 1] && line[last] == line[last - 2] {
+                return false;
+            }
+            if last < n - 2 && line[last] == line[last + 1] && line[last] == line[last + 2] {
+                return false;
+            }
+            if last > 0
+                && last < n - 1
+                && line[last] == line[last - 1]
+                && line[last] == line[last + 1]
+            {
+                return false;
+            }
+            let ones = line.iter().filter(|&&v| v == Some(1)).count();
+            let zeros = line.iter().filter(|&&v| v == Some(0)).count();
+            return zeros <= n / 2 && ones <= n / 2;
+        }
+
+        fn backtrack(
+            line: &mut Vec<Option<u8>>,
+            last: Option<usize>,
+            lines: &Vec<Vec<Option<u8>>>,
+        ) -> bool {
+            if last.is_some() && !valid(line, last.unwrap()) {
+                return false;
+            }
+            if line.iter().all(|v| v.is_some()) {
+                return !lines.contains(line);
+            }
+            for i in 0..line.len() {
+                if line[i].is_none() {
+                    for guess in [0, 1] {
+                        line[i] = Some(guess);
+                        if backtrack(line, Some(i), lines) {
+                            return true;
+                        }
+                        line[i] = None;
+                    }
+                }
+            }
+            return false;
+        }
+
+        let rows: Vec<Vec<_>> = (0..puzzle.height()).map(|y| puzzle[y].clone()).collect();
+        let columns: Vec<Vec<_>> = (0..puzzle.width())
+            .map(|x| (0..puzzle.height()).map(|y| puzzle[y][x]).collect())
+            .collect();
+        let others = |lines: &Vec<Vec<Option<u8>>>, i: usize| -> Vec<Vec<Option<u8>>> {
+            if !unique {
+                return Vec::new();
+            }
+            (0..lines.len())
+                .filter(|&j| j != i && lines[j].iter().all(|v| v.is_some()))
+                .map(|j| lines[j].clone())
+                .collect()
+        };
+
+        let mut hints = Vec::new();
+        for y in 0..puzzle.height() {
+            'next_cell: for x in 0..puzzle.width() {
+                if puzzle[y][x].is_none() {
+                    for guess in [0, 1] {
+                        let mut line = rows[y].clone();
+                        line[x] = Some(guess);
+                        if !backtrack(&mut line, None, &others(&rows, y)) {
+                            hints.push(hint::Hint::new(x, y, guess ^ 1));
+                            continue 'next_cell;
+                        }
+
+                        let mut line = columns[x].clone();
+                        line[y] = Some(guess);
+                        if !backtrack(&mut line, None, &others(&columns, x)) {
+                            hints.push(hint::Hint::new(x, y, guess ^ 1));
+                            continue 'next_cell;
+                        }
+                    }
+                }
+            }
+        }
+        return hints;
+    }
+
+    #[test]
+    fn line_tactics_reference() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let solutions = [
+            ("011001110010101100001011010110100101", 6, 6),
+            (
+                "1010011010101100010100110100110110110010110011000101100100110011",
+                8,
+                8,
+            ),
+            ("01101001100101100110100110010110", 8, 4),
+        ];
+
+        for (codex, width, height) in solutions {
+            let solution = puzzle::Puzzle::from_codex(codex, width, height).unwrap();
+            for _ in 0..50 {
+                // remove a random part of the solution and apply the basic tactics
+                let mut puzzle = solution.clone();
+                for y in 0..height {
+                    for x in 0..width {
+                        if rng.gen_bool(0.7) {
+                            puzzle[y][x] = None;
+                        }
+                    }
+                }
+                let puzzle = analyzer::Stats::from_level(&puzzle, level::Level::Easy).solved;
+
+                assert_eq!(
+                    Tactics::CountGuess.hints(&puzzle),
+                    reference(&puzzle, false)
+                );
+                assert_eq!(Tactics::Uniqueness.hints(&puzzle), reference(&puzzle, true));
+            }
+        }
+    }
+
+    #[test]
+    fn soundness_large() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        for (width, height) in [(30, 30), (24, 10), (10, 24), (16, 28)] {
+            let solution = solver::solve(&puzzle::Puzzle::new(width, height).unwrap()).unwrap();
+            for _ in 0..2 {
+                let mut puzzle = solution.clone();
+                for y in 0..height {
+                    for x in 0..width {
+                        if rng.gen_bool(0.6) {
+                            puzzle[y][x] = None;
+                        }
+                    }
+                }
+                let puzzle = analyzer::Stats::from_level(&puzzle, level::Level::Easy).solved;
+
+                // all tactics but backtracking only give values which are forced
+                for tactic in level::Level::Medium.tactics() {
+                    for hint in tactic.hints(&puzzle) {
+                        assert_eq!(solution[hint.y][hint.x], Some(hint.v));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn soundness_runs() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        for longest in [3, 4] {
+            let rules = puzzle::rules::Rules {
+                run: Some(longest),
+                ..Default::default()
+            };
+            let solution =
+                solver::solve(&puzzle::Puzzle::new_with(12, 12, rules).unwrap()).unwrap();
+            for _ in 0..5 {
+                let mut puzzle = solution.clone();
+                for y in 0..12 {
+                    for x in 0..12 {
+                        if rng.gen_bool(0.6) {
+                            puzzle[y][x] = None;
+                        }
+                    }
+                }
+                let puzzle = analyzer::Stats::from_level(&puzzle, level::Level::Easy).solved;
+                assert_eq!(puzzle.rules().run, Some(longest));
+
+                for tactic in level::Level::Medium.tactics() {
+                    for hint in tactic.hints(&puzzle) {
+                        assert_eq!(solution[hint.y][hint.x], Some(hint.v));
+                    }
+                }
+            }
+        }
+    }
 }
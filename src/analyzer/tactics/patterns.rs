@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Return all the valid completely filled lines of the given width. Every line
+/// is stored as a bitmask where bit i holds the value of cell i. The patterns
+/// are computed once per width and cached for all later calls.
+pub fn patterns(width: usize) -> Arc<Vec<u64>> {
+    static CACHE: OnceLock<Mutex<HashMap<usize, Arc<Vec<u64>>>>> = OnceLock::new();
+
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut cache = cache.lock().unwrap();
+
+    return cache
+        .entry(width)
+        .or_insert_with(|| Arc::new(generate(width)))
+        .clone();
+}
+
+/// Return the bitmask of the known cells and the bitmask of their values.
+pub fn known(line: &[Option<u8>]) -> (u64, u64) {
+    let mut mask = 0;
+    let mut value = 0;
+
+    for (i, cell) in line.iter().enumerate() {
+        if let Some(symbol) = cell {
+            mask |= 1 << i;
+            value |= (*symbol as u64) << i;
+        }
+    }
+    return (mask, value);
+}
+
+/// Generate all the valid lines of the given width.
+fn generate(width: usize) -> Vec<u64> {
+    assert!(width <= 64, "Lines longer than 64 cells are not supported.");
+
+    let mut patterns = Vec::new();
+    extend(width, 0, 0, [0, 0], &mut patterns);
+    return patterns;
+}
+
+/// Extend the partial pattern with the first i cells filled in, in all the ways
+/// which do not break the balance and the no three consecutive rule.
+fn extend(width: usize, i: usize, pattern: u64, count: [usize; 2], patterns: &mut Vec<u64>) {
+    if i == width {
+        patterns.push(pattern);
+        return;
+    }
+
+    for symbol in [0, 1] {
+        if count[symbol] == width / 2 {
+            continue; // there are already enough of this symbol
+        }
+        if i > 1
+            && (pattern >> (i - 1)) & 1 == symbol as u64
+            && (pattern >> (i - 2)) & 1 == symbol as u64
+        {
+            continue; // this would create three consecutive symbols
+        }
+
+        let mut count = count;
+        count[symbol] += 1;
+        extend(
+            width,
+            i + 1,
+            pattern | (symbol as u64) << i,
+            count,
+            patterns,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amount() {
+        let amounts = [(4, 6), (6, 14), (8, 34), (10, 84), (12, 208), (20, 8196)];
+        for (width, amount) in amounts {
+            assert_eq!(patterns(width).len(), amount);
+        }
+    }
+
+    #[test]
+    fn small() {
+        assert_eq!(
+            *patterns(4),
+            vec![0b1100, 0b1010, 0b0110, 0b1001, 0b0101, 0b0011]
+        );
+    }
+
+    #[test]
+    fn known_cells() {
+        assert_eq!(known(&[None, Some(1), Some(0), Some(1)]), (0b1110, 0b1010));
+        assert_eq!(known(&[None; 4]), (0, 0));
+    }
+}
@@ -1,53 +1,49 @@
-use crate::analyzer::tactics::{hint, Tactic};
+use crate::analyzer::tactics::{hint, line, Tactic};
 use crate::puzzle;
 
-/// Tactic applied when seen two consecutive same numbers, put the other number
-/// at both sides of these consecutives. E.g. _00_ becomes 1001.
+/// Tactic applied when seen the longest allowed run of the same numbers, which
+/// is two by default, put the other number at both sides of the run. E.g. _00_
+/// becomes 1001.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Row2;
 
 impl Tactic for Row2 {
     fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint> {
+        let (rows, columns) = line::all(self, puzzle);
+        return line::concat(puzzle, &rows, &columns);
+    }
+}
+
+impl line::SegmentTactic for Row2 {
+    fn segment_hints(&self, puzzle: &puzzle::Puzzle, segment: &line::Segment) -> Vec<hint::Hint> {
+        let values = segment.values(puzzle);
         let mut hints = Vec::new();
+        let (n, wrap) = (values.len(), segment.rules.wrap);
+        // with more than two symbols the other value is not known
+        let longest = match segment.rules.run {
+            Some(longest) if segment.rules.symbols == 2 && (!wrap || longest < n) => longest,
+            _ => return hints,
+        };
 
-        // check horizontal lines
-        for y in 0..puzzle.height() {
-            for x in 0..puzzle.width() - 1 {
-                if puzzle[y][x].is_some() && puzzle[y][x] == puzzle[y][x + 1] {
-                    // west
-                    if x > 0 && puzzle[y][x - 1].is_none() {
-                        hints.push(hint::Hint::new(x - 1, y, puzzle[y][x].unwrap() ^ 1));
-                    }
-                    // east
-                    if x + 2 < puzzle.width() && puzzle[y][x + 2].is_none() {
-                        hints.push(hint::Hint::new(x + 2, y, puzzle[y][x].unwrap() ^ 1));
-                    }
+        // when the line wraps a run can continue from the end at the start
+        let starts = if wrap {
+            n
+        } else {
+            (n + 1).saturating_sub(longest)
+        };
+        for i in 0..starts {
+            let run: Vec<_> = (i..i + longest).map(|j| values[j % n]).collect();
+            if run[0].is_some() && run.iter().all(|v| *v == run[0]) {
+                // before
+                if (i > 0 || wrap) && values[(i + n - 1) % n].is_none() {
+                    hints.push(segment.hint((i + n - 1) % n, run[0].unwrap() ^ 1));
                 }
-            }
-        }
-
-        // check vertical lines
-        for x in 0..puzzle.width() {
-            for y in 0..puzzle.height() - 1 {
-                if puzzle[y][x].is_some() && puzzle[y][x] == puzzle[y + 1][x] {
-                    // north
-                    if y > 0 && puzzle[y - 1][x].is_none() {
-                        let hint = hint::Hint::new(x, y - 1, puzzle[y][x].unwrap() ^ 1);
-                        if !hints.contains(&hint) {
-                            hints.push(hint);
-                        }
-                    }
-                    // south
-                    if y + 2 < puzzle.height() && puzzle[y + 2][x].is_none() {
-                        let hint = hint::Hint::new(x, y + 2, puzzle[y][x].unwrap() ^ 1);
-                        if !hints.contains(&hint) {
-                            hints.push(hint);
-                        }
-                    }
+                // after
+                if (i + longest < n || wrap) && values[(i + longest) % n].is_none() {
+                    hints.push(segment.hint((i + longest) % n, run[0].unwrap() ^ 1));
                 }
             }
         }
-
         return hints;
     }
 }
@@ -83,4 +79,68 @@ mod tests {
                 ]
         );
     }
+
+    #[test]
+    fn triples() {
+        let rules = puzzle::rules::Rules {
+            run: None,
+            ..Default::default()
+        };
+        let puzzle = puzzle::Puzzle::from_codex_with("11c00cd11", 4, 4, rules);
+        assert!(Row2.hints(&puzzle.unwrap()).is_empty());
+    }
+
+    #[test]
+    fn runs() {
+        let rules = |longest| puzzle::rules::Rules {
+            run: Some(longest),
+            ..Default::default()
+        };
+        let puzzle = puzzle::Puzzle::from_codex_with("a000b11zb", 6, 6, rules(3)).unwrap();
+        assert_eq!(
+            Row2.hints(&puzzle),
+            vec![hint::Hint::new(0, 0, 1), hint::Hint::new(4, 0, 1)]
+        );
+        let puzzle = puzzle::Puzzle::from_codex_with("a000b11zb", 6, 6, rules(4)).unwrap();
+        assert!(Row2.hints(&puzzle).is_empty());
+    }
+
+    #[test]
+    fn wrap() {
+        let rules = puzzle::rules::Rules::toroidal();
+        let puzzle = puzzle::Puzzle::from_codex_with("0d0zd", 6, 6, rules).unwrap();
+        assert_eq!(
+            Row2.hints(&puzzle),
+            vec![hint::Hint::new(4, 0, 1), hint::Hint::new(1, 0, 1)]
+        );
+        assert!(Row2
+            .hints(&puzzle::Puzzle::from_codex("0d0zd", 6, 6).unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn diagonal() {
+        // the run on the main diagonal only counts with the diagonal rules
+        let rules = puzzle::rules::Rules::diagonal();
+        let puzzle = puzzle::Puzzle::from_codex_with("g1f1u", 6, 6, rules).unwrap();
+        assert_eq!(
+            Row2.hints(&puzzle),
+            vec![hint::Hint::new(0, 0, 0), hint::Hint::new(3, 3, 0)]
+        );
+        assert!(Row2
+            .hints(&puzzle::Puzzle::from_codex("g1f1u", 6, 6).unwrap())
+            .is_empty());
+    }
+
+    #[test]
+    fn blocked() {
+        // a blocked cell ends the run
+        let puzzle = puzzle::Puzzle::from_codex("a11_bzd", 6, 6).unwrap();
+        assert_eq!(Row2.hints(&puzzle), vec![hint::Hint::new(0, 0, 0)]);
+        let puzzle = puzzle::Puzzle::from_codex("a11czd", 6, 6).unwrap();
+        assert_eq!(
+            Row2.hints(&puzzle),
+            vec![hint::Hint::new(0, 0, 0), hint::Hint::new(3, 0, 0)]
+        );
+    }
 }
@@ -1,42 +1,47 @@
-use crate::analyzer::tactics::{hint, Tactic};
+use crate::analyzer::tactics::{hint, line, Tactic};
 use crate::puzzle;
 
-/// Tactic applied when seen two consecutive same numbers, with a gap in
-/// between. E.g. 0_0 becomes 010.
+/// Tactic applied when seen two runs of the same numbers with a gap in between,
+/// which together with the gap are one longer than the longest allowed run. By
+/// default the runs are single numbers, e.g. 0_0 becomes 010.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Row3;
 
 impl Tactic for Row3 {
     fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint> {
-        let mut hints = Vec::new();
+        let (rows, columns) = line::all(self, puzzle);
+        return line::concat(puzzle, &rows, &columns);
+    }
+}
 
-        // check horizontal lines
-        for y in 0..puzzle.height() {
-            for x in 0..puzzle.width() - 2 {
-                if puzzle[y][x].is_some()
-                    && puzzle[y][x + 1].is_none()
-                    && puzzle[y][x] == puzzle[y][x + 2]
-                {
-                    hints.push(hint::Hint::new(x + 1, y, puzzle[y][x].unwrap() ^ 1));
-                }
-            }
-        }
+impl line::SegmentTactic for Row3 {
+    fn segment_hints(&self, puzzle: &puzzle::Puzzle, segment: &line::Segment) -> Vec<hint::Hint> {
+        let values = segment.values(puzzle);
+        let mut hints = Vec::new();
+        let (n, wrap) = (values.len(), segment.rules.wrap);
+        // with more than two symbols the other value is not known
+        let longest = match segment.rules.run {
+            Some(longest) if segment.rules.symbols == 2 && (!wrap || longest < n) => longest,
+            _ => return hints,
+        };
 
-        // check vertical lines
-        for x in 0..puzzle.width() {
-            for y in 0..puzzle.height() - 2 {
-                if puzzle[y][x].is_some()
-                    && puzzle[y + 1][x].is_none()
-                    && puzzle[y][x] == puzzle[y + 2][x]
-                {
-                    let hint = hint::Hint::new(x, y + 1, puzzle[y][x].unwrap() ^ 1);
-                    if !hints.contains(&hint) {
-                        hints.push(hint);
-                    }
-                }
+        // when the line wraps a window can continue from the end at the start
+        let starts = if wrap {
+            n
+        } else {
+            (n + 1).saturating_sub(longest + 1)
+        };
+        for i in 0..starts {
+            let window: Vec<_> = (i..=i + longest).map(|j| values[j % n]).collect();
+            let gaps: Vec<_> = (1..longest).filter(|&j| window[j].is_none()).collect();
+            if window[0].is_some()
+                && window[longest] == window[0]
+                && gaps.len() == 1
+                && window.iter().all(|v| v.is_none() || *v == window[0])
+            {
+                hints.push(segment.hint((i + gaps[0]) % n, window[0].unwrap() ^ 1));
             }
         }
-
         return hints;
     }
 }
@@ -56,4 +61,29 @@ mod tests {
         let puzzle = puzzle::Puzzle::from_codex("1f01f0", 4, 4).unwrap();
         assert!(Row3.hints(&puzzle) == vec![hint::Hint::new(0, 1, 0), hint::Hint::new(3, 2, 1),]);
     }
+
+    #[test]
+    fn runs() {
+        let rules = |longest| puzzle::rules::Rules {
+            run: Some(longest),
+            ..Default::default()
+        };
+        let puzzle = puzzle::Puzzle::from_codex_with("0a00b11a1bx", 6, 6, rules(3)).unwrap();
+        assert_eq!(
+            Row3.hints(&puzzle),
+            vec![hint::Hint::new(1, 0, 1), hint::Hint::new(2, 1, 0)]
+        );
+        let puzzle = puzzle::Puzzle::from_codex_with("0a00b11a1bx", 6, 6, rules(4)).unwrap();
+        assert!(Row3.hints(&puzzle).is_empty());
+    }
+
+    #[test]
+    fn wrap() {
+        let rules = puzzle::rules::Rules::toroidal();
+        let puzzle = puzzle::Puzzle::from_codex_with("a0c0zd", 6, 6, rules).unwrap();
+        assert_eq!(Row3.hints(&puzzle), vec![hint::Hint::new(0, 0, 1)]);
+        assert!(Row3
+            .hints(&puzzle::Puzzle::from_codex("a0c0zd", 6, 6).unwrap())
+            .is_empty());
+    }
 }
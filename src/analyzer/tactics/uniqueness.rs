@@ -1,49 +1,48 @@
-use crate::analyzer::tactics::{hint, Tactic};
+use crate::analyzer::tactics::{hint, line, Tactic};
 use crate::puzzle;
 
-/// Advanced tactic which uses backtracking on one line, but takes into account
-/// the uniqueness compared to other lines. Guess a value, if no valid unique
-/// line can follow we know the guessed value should be the opposite.
+/// Advanced tactic which looks at all the valid ways to fill in one line, but
+/// takes into account the uniqueness compared to other lines. Guess a value, if
+/// no valid unique line can follow we know the guessed value should be the
+/// opposite. Without the rule that lines are distinct it gives no hints.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Uniqueness;
 
 impl Tactic for Uniqueness {
     fn hints(&self, puzzle: &puzzle::Puzzle) -> Vec<hint::Hint> {
-        let mut hints = Vec::new();
-
-        for y in 0..puzzle.height() {
-            'next_cell: for x in 0..puzzle.width() {
-                if puzzle[y][x].is_none() {
-                    for guess in [0, 1] {
-                        // create the horizontal line
-                        let mut line = puzzle[y].clone();
-                        line[x] = Some(guess);
-                        let lines = lines_cmp(&puzzle, &line, y, true);
-
-                        // check horizontal line
-                        if !backtrack(&mut line, None, &lines) {
-                            hints.push(hint::Hint::new(x, y, guess ^ 1));
-                            continue 'next_cell;
-                        }
-
-                        // create the vertical line
-                        let mut line: Vec<_> = (0..puzzle.height()).map(|y| puzzle[y][x]).collect();
-                        line[y] = Some(guess);
-                        let lines = lines_cmp(&puzzle, &line, x, false);
+        let (rows, columns) = line::all(self, puzzle);
+        return line::merge(puzzle, &rows, &columns);
+    }
+}
 
-                        // check vertical line
-                        if !backtrack(&mut line, None, &lines) {
-                            hints.push(hint::Hint::new(x, y, guess ^ 1));
-                            continue 'next_cell;
-                        }
-                    }
-                }
-            }
+impl line::LineTactic for Uniqueness {
+    fn line_hints(&self, puzzle: &puzzle::Puzzle, line: line::Line) -> Vec<hint::Hint> {
+        let values = line.values(puzzle);
+        let rules = line.rules(puzzle);
+        if !rules.distinct {
+            return Vec::new();
         }
-        return hints;
+        let lines = lines_cmp(puzzle, &values, line);
+
+        line::forced(
+            &values,
+            &line.blocked(puzzle),
+            &line.markers(puzzle),
+            line.clue(puzzle),
+            &exclude(&lines, rules.symbols),
+            &rules,
+        )
+        .into_iter()
+        .map(|(i, v)| line.hint(i, v))
+        .collect()
     }
 }
 
+/// Convert the full lines to the patterns which are excluded as completions.
+fn exclude(lines: &[Vec<Option<u8>>], symbols: u8) -> Vec<u64> {
+    lines.iter().map(|l| line::pattern(l, symbols)).collect()
+}
+
 /// Return true if the base line could possibly collide with the extra line.
 /// Note that when the base line is filled this function returns whether the
 /// base line collides with the extra line.
@@ -60,98 +59,23 @@ fn collision_exists(line_base: &Vec<Option<u8>>, line_extra: &Vec<Option<u8>>) -
 fn lines_cmp(
     puzzle: &puzzle::Puzzle,
     line: &Vec<Option<u8>>,
-    line_i: usize,
-    horizontal: bool,
+    current: line::Line,
 ) -> Vec<Vec<Option<u8>>> {
-    // first get all the lines not equal to the given line
-    let lines: Vec<Vec<Option<u8>>> = if horizontal {
-        (0..puzzle.height())
-            .filter(|&y| y != line_i)
-            .map(|y| puzzle[y].clone())
-            .collect()
-    } else {
-        (0..puzzle.width())
-            .filter(|&x| x != line_i)
-            .map(|x| (0..puzzle.height()).map(|y| puzzle[y][x]).collect())
-            .collect()
-    };
+    // first get all the lines not equal to the given line, only the lines with
+    // the same blocked cells can be equal to it
+    let lines = current.parallel(puzzle);
+    let blocked = current.blocked(puzzle);
 
     // filter out the lines which are filled and possible collides with the given line
     return lines
         .into_iter()
-        .filter(|l| l.iter().all(|v| v.is_some())) // check if the line is full
+        .filter(|&l| l != current && l.blocked(puzzle) == blocked)
+        .filter(|l| l.full(puzzle)) // check if the line is full
+        .map(|l| l.values(puzzle))
         .filter(|l| collision_exists(&line, &l)) // check if the line possibly collides
         .collect();
 }
 
-/// Backtrack on the given line and return whether a valid state exists or not.
-fn backtrack(
-    line: &mut Vec<Option<u8>>,
-    last: Option<usize>,
-    lines: &Vec<Vec<Option<u8>>>,
-) -> bool {
-    if last.is_some() && !valid(line, last.unwrap()) {
-        return false; // the line is invalid
-    }
-
-    if line.iter().all(|v| v.is_some()) {
-        // the line is valid, completely filled and does not collide
-        return lines.iter().all(|l| !collision_exists(&line, &l));
-    }
-
-    for i in 0..line.len() {
-        if line[i].is_none() {
-            for guess in [0, 1] {
-                line[i] = Some(guess);
-
-                if backtrack(line, Some(i), lines) {
-                    return true;
-                }
-
-                line[i] = None;
-            }
-        }
-    }
-
-    return false; // no valid state was found
-}
-
-/// Check whether a given row is still valid given the last changed index.
-fn valid(line: &Vec<Option<u8>>, last: usize) -> bool {
-    // check [. . last]
-    if last > 1 && line[last] == line[last - 1] && line[last] == line[last - 2] {
-        return false;
-    }
-
-    // check [last . .]
-    if last < line.len() - 2 && line[last] == line[last + 1] && line[last] == line[last + 2] {
-        return false;
-    }
-
-    // check [. last .]
-    if last > 0
-        && last < line.len() - 1
-        && line[last] == line[last - 1]
-        && line[last] == line[last + 1]
-    {
-        return false;
-    }
-
-    // check counts
-    let mut count: [usize; 2] = [0, 0];
-    for i in 0..line.len() {
-        if let Some(symbol) = line[i] {
-            count[symbol as usize] += 1;
-        }
-    }
-    if count[0] > line.len() / 2 || count[1] > line.len() / 2 {
-        return false;
-    }
-
-    // everything is valid
-    return true;
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,8 +106,7 @@ mod tests {
             lines_cmp(
                 &puzzle::Puzzle::from_codex("001a011000110c", 4, 4).unwrap(),
                 &vec![Some(0), None, None, None],
-                3,
-                true,
+                line::Line::Row(3),
             ) == vec![
                 vec![Some(0), Some(1), Some(1), Some(0)],
                 vec![Some(0), Some(0), Some(1), Some(1)],
@@ -197,8 +120,7 @@ mod tests {
             lines_cmp(
                 &puzzle::Puzzle::from_codex("0000011a1a1a1a01", 4, 4).unwrap(),
                 &vec![Some(0), Some(1), None, None],
-                1,
-                false,
+                line::Line::Column(1),
             ) == vec![vec![Some(0), Some(1), Some(1), Some(0)],]
         );
     }
@@ -218,4 +140,11 @@ mod tests {
                 == vec![hint::Hint::new(3, 1, 1), hint::Hint::new(3, 2, 0),]
         );
     }
+
+    #[test]
+    fn unruly() {
+        let puzzle =
+            puzzle::Puzzle::from_codex_with("10100b10b11b0", 4, 4, puzzle::rules::Rules::unruly());
+        assert!(Uniqueness.hints(&puzzle.unwrap()).is_empty());
+    }
 }
@@ -1,21 +1,51 @@
-use binairo::{analyzer::level, generator, solver};
+use binairo::{
+    analyzer::level,
+    generator::{self, batch},
+    solver,
+};
 
 fn main() {
-    for _ in 0..45 {
-        let size = 12;
-        let gen = generator::gen(size, size, level::Level::Easy, None).unwrap();
-        println!("{} {} {}", size, gen.codex(), solver::solve(&gen).unwrap().codex());
-    }
-    println!();
-    for _ in 0..14 {
-        let size = 8;
-        let gen = generator::gen(size, size, level::Level::Medium, None).unwrap();
-        println!("{} {} {}", size, gen.codex(), solver::solve(&gen).unwrap().codex());
-    }
-    println!();
-    for _ in 0..1 {
-        let size = 8;
-        let gen = generator::gen(size, size, level::Level::Inhuman, None).unwrap();
-        println!("{} {} {}", size, gen.codex(), solver::solve(&gen).unwrap().codex());
+    // the seed of an earlier run can be given to generate the same puzzles again
+    let seed = match std::env::args().nth(1) {
+        Some(seed) => seed.parse().expect("The seed has to be a number."),
+        None => rand::random(),
+    };
+    eprintln!("seed {}", seed);
+    let sets = [
+        (12, level::Level::Easy, 45),
+        (8, level::Level::Medium, 14),
+        (8, level::Level::Inhuman, 1),
+    ];
+
+    for (size, level, amount) in sets {
+        let options = generator::Options::new(level);
+        let progress = |done, total| eprint!("\r{}/{}", done, total);
+        let generated = batch::batch(size, size, &options, amount, seed, 0, progress);
+        eprintln!();
+
+        // a failed generation is reported and left out of the set
+        let mut failed = 0;
+        for gen in generated {
+            let puzzle = match gen.puzzle {
+                Ok(puzzle) => puzzle,
+                Err(error) => {
+                    eprintln!("{}", error);
+                    failed += 1;
+                    continue;
+                }
+            };
+            let solution = solver::solve(&puzzle).unwrap();
+            println!(
+                "{} {} {} {:?}",
+                size,
+                puzzle.codex(),
+                solution.codex(),
+                gen.time
+            );
+        }
+        if failed > 0 {
+            eprintln!("{} of {} puzzles could not be generated", failed, amount);
+        }
+        println!();
     }
 }
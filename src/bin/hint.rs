@@ -8,6 +8,7 @@ fn main() {
     let tactics = vec![
         tactics::Tactics::Row2,
         tactics::Tactics::Row3,
+        tactics::Tactics::Markers,
         tactics::Tactics::CountFixed,
         tactics::Tactics::CountGuess,
         tactics::Tactics::Uniqueness,
@@ -0,0 +1,175 @@
+use crate::puzzle::marker;
+use std::result;
+
+/// A pencil mark which a player writes on the puzzle while solving it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mark {
+    // the value the player expects in the cell
+    Dot {
+        x: usize,
+        y: usize,
+        v: u8,
+    },
+    // a value the player ruled out for the cell
+    Not {
+        x: usize,
+        y: usize,
+        v: u8,
+    },
+    // two cells which the player expects to be equal or opposite
+    Link {
+        a: (usize, usize),
+        b: (usize, usize),
+        marker: marker::Marker,
+    },
+}
+
+/// A mark together with whether the player confirmed it, only the confirmed
+/// marks are used when looking for hints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Annotation {
+    pub mark: Mark,
+    pub confirmed: bool,
+}
+
+impl Mark {
+    /// Return the cells (x, y) this mark is written on.
+    pub fn cells(&self) -> Vec<(usize, usize)> {
+        match *self {
+            Mark::Dot { x, y, .. } | Mark::Not { x, y, .. } => vec![(x, y)],
+            Mark::Link { a, b, .. } => vec![a, b],
+        }
+    }
+
+    /// Return the same mark where the cells of a link are in reading order, such
+    /// that a link is equal to the link between the same cells in reverse.
+    pub fn normal(&self) -> Mark {
+        match *self {
+            Mark::Link { a, b, marker } if (b.1, b.0) < (a.1, a.0) => {
+                Mark::Link { a: b, b: a, marker }
+            }
+            mark => mark,
+        }
+    }
+
+    /// Return the side of the first cell of a normal link at which the second
+    /// cell is, if they are neighbours.
+    pub fn direction(&self) -> Option<marker::Direction> {
+        match *self {
+            Mark::Link { a, b, .. } if b == (a.0 + 1, a.1) => Some(marker::Direction::Right),
+            Mark::Link { a, b, .. } if b == (a.0, a.1 + 1) => Some(marker::Direction::Down),
+            _ => None,
+        }
+    }
+}
+
+impl Annotation {
+    /// Return the codex representation of this annotation, a letter for the kind
+    /// of mark followed by its numbers separated by dots. The letter is d for a
+    /// dot, n for a ruled out value, e for an equal and o for an opposite link,
+    /// and is written in uppercase when the mark is confirmed.
+    pub fn codex(&self) -> String {
+        let (letter, numbers) = match self.mark {
+            Mark::Dot { x, y, v } => ('d', vec![x, y, v as usize]),
+            Mark::Not { x, y, v } => ('n', vec![x, y, v as usize]),
+            Mark::Link { a, b, marker } => match marker {
+                marker::Marker::Equal => ('e', vec![a.0, a.1, b.0, b.1]),
+                marker::Marker::Opposite => ('o', vec![a.0, a.1, b.0, b.1]),
+            },
+        };
+        let letter = match self.confirmed {
+            true => letter.to_ascii_uppercase(),
+            false => letter,
+        };
+        let numbers: Vec<_> = numbers.iter().map(|n| n.to_string()).collect();
+        return format!("{}{}", letter, numbers.join("."));
+    }
+
+    /// Create an annotation from its codex representation.
+    pub fn from_codex(codex: &str) -> result::Result<Annotation, String> {
+        let invalid = || format!("The mark {:?} is invalid.", codex);
+        let letter = codex.chars().next().ok_or_else(invalid)?;
+        let numbers = codex[letter.len_utf8()..]
+            .split('.')
+            .map(|n| n.parse::<usize>())
+            .collect::<result::Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+
+        let mark = match (letter.to_ascii_lowercase(), numbers.as_slice()) {
+            ('d', &[x, y, v]) if v <= u8::MAX as usize => Mark::Dot { x, y, v: v as u8 },
+            ('n', &[x, y, v]) if v <= u8::MAX as usize => Mark::Not { x, y, v: v as u8 },
+            ('e', &[ax, ay, bx, by]) => Mark::Link {
+                a: (ax, ay),
+                b: (bx, by),
+                marker: marker::Marker::Equal,
+            },
+            ('o', &[ax, ay, bx, by]) => Mark::Link {
+                a: (ax, ay),
+                b: (bx, by),
+                marker: marker::Marker::Opposite,
+            },
+            _ => return Err(invalid()),
+        };
+        return Ok(Annotation {
+            mark,
+            confirmed: letter.is_ascii_uppercase(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links() {
+        let link = Mark::Link {
+            a: (2, 1),
+            b: (2, 0),
+            marker: marker::Marker::Equal,
+        };
+        assert_eq!(
+            link.normal(),
+            Mark::Link {
+                a: (2, 0),
+                b: (2, 1),
+                marker: marker::Marker::Equal,
+            }
+        );
+        assert_eq!(link.normal().direction(), Some(marker::Direction::Down));
+        assert_eq!(link.direction(), None);
+        assert_eq!(link.cells(), vec![(2, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn codex() {
+        let annotations = [
+            Annotation {
+                mark: Mark::Dot { x: 3, y: 0, v: 1 },
+                confirmed: true,
+            },
+            Annotation {
+                mark: Mark::Not { x: 0, y: 12, v: 2 },
+                confirmed: false,
+            },
+            Annotation {
+                mark: Mark::Link {
+                    a: (1, 1),
+                    b: (4, 1),
+                    marker: marker::Marker::Opposite,
+                },
+                confirmed: false,
+            },
+        ];
+        let codices: Vec<_> = annotations.iter().map(|a| a.codex()).collect();
+        assert_eq!(codices, vec!["D3.0.1", "n0.12.2", "o1.1.4.1"]);
+        for (annotation, codex) in annotations.iter().zip(codices) {
+            assert_eq!(Annotation::from_codex(&codex), Ok(*annotation));
+        }
+
+        assert!(Annotation::from_codex("").is_err());
+        assert!(Annotation::from_codex("d1.2").is_err());
+        assert!(Annotation::from_codex("x1.2.0").is_err());
+        assert!(Annotation::from_codex("e1.2.a.3").is_err());
+    }
+}
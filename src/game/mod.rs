@@ -0,0 +1,593 @@
+use crate::{
+    analyzer::tactics::{self, hint::Hint, Tactic},
+    puzzle::{self, marker, rules},
+};
+use std::result;
+
+pub mod mark;
+
+use mark::{Annotation, Mark};
+
+/// The state of a puzzle while a player solves it, that is the values the
+/// player filled in and the pencil marks written on it, with the history of the
+/// changes so they can be undone and redone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    givens: puzzle::Puzzle,
+    puzzle: puzzle::Puzzle,       // the givens and the values filled in
+    annotations: Vec<Annotation>, // the marks in the order they were written
+    undo: Vec<Change>,            // the changes to undo, the last one first
+    redo: Vec<Change>,            // the undone changes, the last one first
+}
+
+/// A change to the state of a game, which holds what is needed to revert it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Change {
+    Fill {
+        x: usize,
+        y: usize,
+        before: Option<u8>,
+        after: Option<u8>,
+    },
+    Insert(usize, Annotation),
+    Remove(usize, Annotation),
+    Replace(usize, Annotation, Annotation), // the annotation before and after
+}
+
+impl Change {
+    /// Return the change which reverts this change.
+    fn inverse(&self) -> Change {
+        match *self {
+            Change::Fill {
+                x,
+                y,
+                before,
+                after,
+            } => Change::Fill {
+                x,
+                y,
+                before: after,
+                after: before,
+            },
+            Change::Insert(i, annotation) => Change::Remove(i, annotation),
+            Change::Remove(i, annotation) => Change::Insert(i, annotation),
+            Change::Replace(i, before, after) => Change::Replace(i, after, before),
+        }
+    }
+}
+
+impl Game {
+    /// Start a game on the given puzzle, where its values are the givens.
+    pub fn new(puzzle: &puzzle::Puzzle) -> Game {
+        Game {
+            givens: puzzle.clone(),
+            puzzle: puzzle.clone(),
+            annotations: Vec::new(),
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// Return the puzzle with only the givens.
+    pub fn givens(&self) -> &puzzle::Puzzle {
+        &self.givens
+    }
+
+    /// Return the puzzle with the givens and the values filled in.
+    pub fn puzzle(&self) -> &puzzle::Puzzle {
+        &self.puzzle
+    }
+
+    /// Return all the marks with whether they are confirmed.
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Fill in the cell at (x, y) with the given value, or empty it if none is
+    /// given.
+    pub fn fill(&mut self, x: usize, y: usize, v: Option<u8>) -> result::Result<(), String> {
+        self.check_cell(x, y, v)?;
+        if self.givens[y][x].is_some() {
+            return Err("A given cell can not be changed.".to_string());
+        }
+        let before = self.puzzle[y][x];
+        if before != v {
+            self.perform(Change::Fill {
+                x,
+                y,
+                before,
+                after: v,
+            });
+        }
+        return Ok(());
+    }
+
+    /// Write the given mark on the puzzle, it starts out unconfirmed.
+    pub fn mark(&mut self, mark: Mark) -> result::Result<(), String> {
+        let mark = mark.normal();
+        self.check_mark(&mark)?;
+        if self.find(&mark).is_some() {
+            return Err("The mark is already written.".to_string());
+        }
+        let annotation = Annotation {
+            mark,
+            confirmed: false,
+        };
+        self.perform(Change::Insert(self.annotations.len(), annotation));
+        return Ok(());
+    }
+
+    /// Confirm the given mark, or take back its confirmation.
+    pub fn confirm(&mut self, mark: Mark, confirmed: bool) -> result::Result<(), String> {
+        let i = self.written(&mark)?;
+        let before = self.annotations[i];
+        if before.confirmed != confirmed {
+            let after = Annotation {
+                confirmed,
+                ..before
+            };
+            self.perform(Change::Replace(i, before, after));
+        }
+        return Ok(());
+    }
+
+    /// Erase the given mark from the puzzle.
+    pub fn erase(&mut self, mark: Mark) -> result::Result<(), String> {
+        let i = self.written(&mark)?;
+        self.perform(Change::Remove(i, self.annotations[i]));
+        return Ok(());
+    }
+
+    /// Undo the last change, return whether there was a change to undo.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(change) => {
+                self.apply(change.inverse());
+                self.redo.push(change);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redo the last undone change, return whether there was a change to redo.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(change) => {
+                self.apply(change);
+                self.undo.push(change);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Return the puzzle with all that follows from the confirmed marks alone
+    /// filled in, where the confirmed links between neighbours are markers. The
+    /// other hints are then found on this puzzle.
+    pub fn premises(&self) -> result::Result<puzzle::Puzzle, String> {
+        let contradiction = || Err("The confirmed marks contradict the puzzle.".to_string());
+        let confirmed = self.confirmed();
+        let symbols = self.puzzle.rules().symbols;
+        let mut premises = self.puzzle.clone();
+
+        for mark in &confirmed {
+            if let (Mark::Link { a, marker, .. }, Some(direction)) = (mark, mark.direction()) {
+                match premises.marker(a.0, a.1, direction) {
+                    Some(given) if given != *marker => return contradiction(),
+                    _ => premises.set_marker(a.0, a.1, direction, Some(*marker)),
+                }
+            }
+        }
+
+        // a linked cell follows from the other cell, unless it has to differ
+        // from it while there are more than two symbols
+        let follow = |marker: marker::Marker, v: u8| match (marker, symbols) {
+            (marker::Marker::Equal, _) => Some(v),
+            (marker::Marker::Opposite, 2) => Some(marker.apply(v)),
+            (marker::Marker::Opposite, _) => None,
+        };
+
+        // keep filling in the cells until the marks give nothing new
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for mark in &confirmed {
+                let ((x, y), v) = match *mark {
+                    Mark::Dot { x, y, v } => ((x, y), Some(v)),
+                    Mark::Not { x, y, v } => {
+                        let left: Vec<_> = (0..symbols)
+                            .filter(|&s| !confirmed.contains(&Mark::Not { x, y, v: s }))
+                            .collect();
+                        if premises[y][x] == Some(v) || left.is_empty() {
+                            return contradiction();
+                        }
+                        ((x, y), (left.len() == 1).then_some(left[0]))
+                    }
+                    Mark::Link { a, b, marker } => match (premises[a.1][a.0], premises[b.1][b.0]) {
+                        (Some(u), Some(w)) if !marker.allows(u, w) => return contradiction(),
+                        (Some(u), None) => (b, follow(marker, u)),
+                        (None, Some(w)) => (a, follow(marker, w)),
+                        _ => continue,
+                    },
+                };
+                match (premises[y][x], v) {
+                    (None, Some(v)) => {
+                        premises[y][x] = Some(v);
+                        changed = true;
+                    }
+                    (Some(u), Some(v)) if u != v => return contradiction(),
+                    _ => (),
+                }
+            }
+        }
+        return Ok(premises);
+    }
+
+    /// Return the hints relative to what the player has written, where the
+    /// confirmed marks are taken as premises. The cells which follow from the
+    /// marks alone come first, otherwise the hints of the first of the given
+    /// tactics which gives any.
+    pub fn hints(&self, tactics: &[tactics::Tactics]) -> result::Result<Vec<Hint>, String> {
+        let premises = self.premises()?;
+        let confirmed = self.confirmed();
+
+        // the player already expects the values of the confirmed dots
+        let hints: Vec<_> = self
+            .cells()
+            .into_iter()
+            .filter(|&(x, y)| self.puzzle[y][x].is_none())
+            .filter_map(|(x, y)| premises[y][x].map(|v| Hint::new(x, y, v)))
+            .filter(|hint| {
+                !confirmed.contains(&Mark::Dot {
+                    x: hint.x,
+                    y: hint.y,
+                    v: hint.v,
+                })
+            })
+            .collect();
+        if !hints.is_empty() {
+            return Ok(hints);
+        }
+
+        for tactic in tactics {
+            let hints = tactic.hints(&premises);
+            if !hints.is_empty() {
+                if hints.iter().any(|hint| {
+                    confirmed.contains(&Mark::Not {
+                        x: hint.x,
+                        y: hint.y,
+                        v: hint.v,
+                    })
+                }) {
+                    return Err("The confirmed marks contradict the puzzle.".to_string());
+                }
+                return Ok(hints);
+            }
+        }
+        return Ok(Vec::new());
+    }
+
+    /// Return the codex representation of this game, which is the codex of the
+    /// givens, the codex of the values filled in and the codices of the marks
+    /// separated by commas, all separated by semicolons. The history of the
+    /// changes is not part of it.
+    pub fn codex(&self) -> String {
+        let (width, height) = (self.puzzle.width(), self.puzzle.height());
+        let mut filled = puzzle::Puzzle::new_with(width, height, *self.puzzle.rules()).unwrap();
+        for (x, y) in self.cells() {
+            if self.givens[y][x].is_none() {
+                filled[y][x] = self.puzzle[y][x];
+            }
+        }
+        let marks: Vec<_> = self.annotations.iter().map(|a| a.codex()).collect();
+        return format!(
+            "{};{};{}",
+            self.givens.codex(),
+            filled.codex(),
+            marks.join(",")
+        );
+    }
+
+    /// Create a game on a puzzle which follows the given rules from a codex
+    /// string.
+    pub fn from_codex(
+        codex: &str,
+        width: usize,
+        height: usize,
+        rules: rules::Rules,
+    ) -> result::Result<Game, String> {
+        let sections: Vec<_> = codex.split(';').collect();
+        if sections.len() != 3 {
+            return Err("The codex of a game has three sections.".to_string());
+        }
+
+        let mut game = Game::new(&puzzle::Puzzle::from_codex_with(
+            sections[0],
+            width,
+            height,
+            rules,
+        )?);
+        let filled = puzzle::Puzzle::from_codex_with(sections[1], width, height, rules)?;
+        for (x, y) in game.cells() {
+            if filled[y][x].is_some() {
+                game.fill(x, y, filled[y][x])?;
+            }
+        }
+        for codex in sections[2].split(',').filter(|codex| !codex.is_empty()) {
+            let annotation = Annotation::from_codex(codex)?;
+            game.mark(annotation.mark)?;
+            game.confirm(annotation.mark, annotation.confirmed)?;
+        }
+
+        // the game starts out without a history
+        game.undo.clear();
+        return Ok(game);
+    }
+
+    /// Make the given change and remember it, which leaves nothing to redo.
+    fn perform(&mut self, change: Change) {
+        self.apply(change);
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    /// Make the given change to the state.
+    fn apply(&mut self, change: Change) {
+        match change {
+            Change::Fill { x, y, after, .. } => self.puzzle[y][x] = after,
+            Change::Insert(i, annotation) => self.annotations.insert(i, annotation),
+            Change::Remove(i, _) => {
+                self.annotations.remove(i);
+            }
+            Change::Replace(i, _, after) => self.annotations[i] = after,
+        }
+    }
+
+    /// Return the coordinates (x, y) of all the cells, row by row.
+    fn cells(&self) -> Vec<(usize, usize)> {
+        let (width, height) = (self.puzzle.width(), self.puzzle.height());
+        (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect()
+    }
+
+    /// Return all the confirmed marks.
+    fn confirmed(&self) -> Vec<Mark> {
+        self.annotations
+            .iter()
+            .filter(|annotation| annotation.confirmed)
+            .map(|annotation| annotation.mark)
+            .collect()
+    }
+
+    /// Return the index of the given mark, if it is written.
+    fn find(&self, mark: &Mark) -> Option<usize> {
+        let mark = mark.normal();
+        self.annotations.iter().position(|a| a.mark == mark)
+    }
+
+    /// Return the index of the given mark, or an error if it is not written.
+    fn written(&self, mark: &Mark) -> result::Result<usize, String> {
+        self.find(mark)
+            .ok_or_else(|| "The mark is not written.".to_string())
+    }
+
+    /// Check that the given mark can be written on the puzzle.
+    fn check_mark(&self, mark: &Mark) -> result::Result<(), String> {
+        let v = match *mark {
+            Mark::Dot { v, .. } | Mark::Not { v, .. } => Some(v),
+            Mark::Link { a, b, .. } if a == b => {
+                return Err("A cell can not be linked to itself.".to_string())
+            }
+            Mark::Link { .. } => None,
+        };
+        for (x, y) in mark.cells() {
+            self.check_cell(x, y, v)?;
+        }
+        return Ok(());
+    }
+
+    /// Check that the cell at (x, y) is an open cell of the puzzle, and that the
+    /// given value is one of the symbols.
+    fn check_cell(&self, x: usize, y: usize, v: Option<u8>) -> result::Result<(), String> {
+        if x >= self.puzzle.width() || y >= self.puzzle.height() {
+            return Err("The cell is outside of the puzzle.".to_string());
+        }
+        if self.puzzle.blocked(x, y) {
+            return Err("A blocked cell can not be changed.".to_string());
+        }
+        if v.is_some_and(|v| v >= self.puzzle.rules().symbols) {
+            return Err("The value is not one of the symbols.".to_string());
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history() {
+        let mut game = Game::new(&puzzle::Puzzle::from_codex("1o", 4, 4).unwrap());
+        let dot = Mark::Dot { x: 1, y: 0, v: 0 };
+        game.fill(2, 0, Some(0)).unwrap();
+        game.mark(dot).unwrap();
+        game.confirm(dot, true).unwrap();
+        assert_eq!(game.puzzle()[0][2], Some(0));
+        assert_eq!(
+            game.annotations(),
+            &[Annotation {
+                mark: dot,
+                confirmed: true,
+            }]
+        );
+
+        assert!(game.undo());
+        assert!(!game.annotations()[0].confirmed);
+        assert!(game.undo());
+        assert!(game.undo());
+        assert!(!game.undo());
+        assert_eq!(game.puzzle(), game.givens());
+        assert!(game.annotations().is_empty());
+
+        assert!(game.redo());
+        assert!(game.redo());
+        assert_eq!(game.puzzle()[0][2], Some(0));
+        assert_eq!(game.annotations().len(), 1);
+
+        // a new change leaves nothing to redo
+        game.erase(dot).unwrap();
+        assert!(!game.redo());
+        assert!(game.annotations().is_empty());
+        assert!(game.undo());
+        assert_eq!(game.annotations()[0].mark, dot);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut puzzle = puzzle::Puzzle::from_codex("1o", 4, 4).unwrap();
+        puzzle.set_blocked(3, 3, true);
+        let mut game = Game::new(&puzzle);
+        assert!(game.fill(0, 0, Some(0)).is_err());
+        assert!(game.fill(4, 0, Some(0)).is_err());
+        assert!(game.fill(1, 0, Some(2)).is_err());
+        assert!(game.fill(3, 3, Some(1)).is_err());
+        assert!(game.mark(Mark::Not { x: 1, y: 1, v: 2 }).is_err());
+        assert!(game.confirm(Mark::Dot { x: 1, y: 1, v: 0 }, true).is_err());
+
+        // a link is the same in both directions
+        let link = |a, b| Mark::Link {
+            a,
+            b,
+            marker: marker::Marker::Equal,
+        };
+        assert!(game.mark(link((1, 1), (1, 1))).is_err());
+        assert!(game.mark(link((2, 1), (1, 1))).is_ok());
+        assert!(game.mark(link((1, 1), (2, 1))).is_err());
+        assert!(game.confirm(link((1, 1), (2, 1)), true).is_ok());
+        assert_eq!(game.annotations()[0].mark, link((1, 1), (2, 1)));
+    }
+
+    #[test]
+    fn codex() {
+        let mut game = Game::new(&puzzle::Puzzle::from_codex("1o", 4, 4).unwrap());
+        game.fill(1, 0, Some(0)).unwrap();
+        game.fill(3, 3, Some(1)).unwrap();
+        game.mark(Mark::Not { x: 2, y: 1, v: 1 }).unwrap();
+        game.confirm(Mark::Not { x: 2, y: 1, v: 1 }, true).unwrap();
+        game.mark(Mark::Link {
+            a: (0, 1),
+            b: (0, 3),
+            marker: marker::Marker::Opposite,
+        })
+        .unwrap();
+
+        let codex = game.codex();
+        assert_eq!(codex, "1o;a0m1;N2.1.1,o0.1.0.3");
+        let read = Game::from_codex(&codex, 4, 4, rules::Rules::default()).unwrap();
+        assert_eq!(read.givens(), game.givens());
+        assert_eq!(read.puzzle(), game.puzzle());
+        assert_eq!(read.annotations(), game.annotations());
+        assert_eq!(read.codex(), codex);
+
+        // the history is not part of the codex
+        let mut read = read;
+        assert!(!read.undo());
+
+        let read = |codex| Game::from_codex(codex, 4, 4, rules::Rules::default());
+        assert!(read("1o;p").is_err());
+        assert!(read("1o;0o;").is_err());
+        assert!(read("1o;p;d4.0.1").is_err());
+        assert!(read("1o;p;d1.0.1,D1.0.1").is_err());
+    }
+
+    #[test]
+    fn premises() {
+        let mut game = Game::new(&puzzle::Puzzle::new(4, 4).unwrap());
+        let marks = [
+            Mark::Not { x: 0, y: 0, v: 0 },
+            Mark::Link {
+                a: (0, 0),
+                b: (2, 0),
+                marker: marker::Marker::Opposite,
+            },
+            Mark::Link {
+                a: (1, 1),
+                b: (1, 2),
+                marker: marker::Marker::Equal,
+            },
+        ];
+        for mark in marks {
+            game.mark(mark).unwrap();
+        }
+
+        // only the confirmed marks are premises
+        assert_eq!(game.premises().unwrap(), *game.puzzle());
+        for mark in marks {
+            game.confirm(mark, true).unwrap();
+        }
+        let premises = game.premises().unwrap();
+        assert_eq!(premises[0][0], Some(1));
+        assert_eq!(premises[0][2], Some(0));
+        assert_eq!(
+            premises.marker(1, 1, marker::Direction::Down),
+            Some(marker::Marker::Equal)
+        );
+        assert_eq!(premises.givens(), 2);
+
+        // a mark which contradicts the others
+        let dot = Mark::Dot { x: 2, y: 0, v: 1 };
+        game.mark(dot).unwrap();
+        game.confirm(dot, true).unwrap();
+        assert!(game.premises().is_err());
+        assert!(game.hints(&[tactics::Tactics::Row2]).is_err());
+    }
+
+    #[test]
+    fn premises_trinairo() {
+        let rules = rules::Rules::trinairo();
+        let mut game = Game::new(&puzzle::Puzzle::new_with(6, 6, rules).unwrap());
+        game.fill(0, 0, Some(2)).unwrap();
+        let marks = [
+            Mark::Link {
+                a: (0, 0),
+                b: (5, 5),
+                marker: marker::Marker::Opposite,
+            },
+            Mark::Not { x: 3, y: 3, v: 0 },
+        ];
+        for mark in marks {
+            game.mark(mark).unwrap();
+            game.confirm(mark, true).unwrap();
+        }
+
+        // an opposite link or a ruled out value leave two of the three symbols
+        assert_eq!(game.premises().unwrap(), *game.puzzle());
+        let not = Mark::Not { x: 3, y: 3, v: 2 };
+        game.mark(not).unwrap();
+        game.confirm(not, true).unwrap();
+        assert_eq!(game.premises().unwrap()[3][3], Some(1));
+    }
+
+    #[test]
+    fn hints() {
+        let mut game = Game::new(&puzzle::Puzzle::new(4, 4).unwrap());
+        let tactics = [tactics::Tactics::Row2, tactics::Tactics::Row3];
+        game.fill(0, 0, Some(1)).unwrap();
+        let dot = Mark::Dot { x: 1, y: 0, v: 1 };
+        game.mark(dot).unwrap();
+        assert_eq!(game.hints(&tactics), Ok(Vec::new()));
+
+        // the confirmed dot is used, but is no hint itself
+        game.confirm(dot, true).unwrap();
+        assert_eq!(game.hints(&tactics), Ok(vec![Hint::new(2, 0, 0)]));
+
+        // the cells which follow from the marks come first
+        let not = Mark::Not { x: 3, y: 3, v: 1 };
+        game.mark(not).unwrap();
+        game.confirm(not, true).unwrap();
+        assert_eq!(game.hints(&tactics), Ok(vec![Hint::new(3, 3, 0)]));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{generator, puzzle};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::{result, thread, time};
+
+/// A puzzle generated as part of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Generated {
+    pub index: usize,
+    pub seed: u64,
+    pub puzzle: result::Result<puzzle::Puzzle, String>,
+    pub time: time::Duration,
+}
+
+/// Generate the given amount of puzzles on the given number of threads, or on
+/// as many threads as are available if zero is given. Every puzzle is generated
+/// from a seed which only depends on the base seed and its index, so the result
+/// does not depend on the number of threads. The progress function is called
+/// with the number of finished puzzles every time a puzzle is finished.
+pub fn batch<F: Fn(usize, usize) + Sync>(
+    width: usize,
+    height: usize,
+    options: &generator::Options,
+    amount: usize,
+    seed: u64,
+    threads: usize,
+    progress: F,
+) -> Vec<Generated> {
+    let threads = match threads {
+        0 => thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    };
+
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(amount));
+
+    thread::scope(|scope| {
+        for _ in 0..threads.min(amount) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= amount {
+                    return;
+                }
+
+                let seed = derive(seed, index as u64);
+                let start = time::Instant::now();
+                let puzzle = generator::gen_with(width, height, options, Some(seed));
+
+                results.lock().unwrap().push(Generated {
+                    index,
+                    seed,
+                    puzzle,
+                    time: start.elapsed(),
+                });
+                progress(done.fetch_add(1, Ordering::SeqCst) + 1, amount);
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|generated| generated.index);
+    return results;
+}
+
+/// Derive the seed of the puzzle with the given index from the base seed, by
+/// mixing the bits with the splitmix64 finalizer.
+pub fn derive(seed: u64, index: u64) -> u64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    return z ^ (z >> 31);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::level;
+
+    #[test]
+    fn deterministic() {
+        let options = generator::Options::new(level::Level::Easy);
+        let calls = AtomicUsize::new(0);
+        let progress = |done: usize, total: usize| {
+            assert!(done >= 1 && done <= total);
+            calls.fetch_add(1, Ordering::SeqCst);
+        };
+
+        let single = batch(4, 4, &options, 6, 42, 1, &progress);
+        let multi = batch(4, 4, &options, 6, 42, 3, &progress);
+        assert_eq!(calls.load(Ordering::SeqCst), 12);
+
+        assert_eq!(single.len(), 6);
+        for (a, b) in single.iter().zip(multi.iter()) {
+            assert_eq!(a.index, b.index);
+            assert_eq!(a.seed, b.seed);
+            assert_eq!(a.puzzle, b.puzzle);
+            assert!(a.puzzle.is_ok());
+        }
+    }
+
+    #[test]
+    fn seeds() {
+        assert_eq!(derive(42, 3), derive(42, 3));
+        assert_ne!(derive(42, 3), derive(42, 4));
+        assert_ne!(derive(42, 3), derive(43, 3));
+    }
+}
@@ -0,0 +1,209 @@
+use crate::{
+    analyzer::level,
+    generator::{self, batch},
+    puzzle::{self, rules},
+};
+use rand::SeedableRng;
+use std::collections::{HashMap, HashSet};
+use std::result;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The number of solutions tried for a day before giving up on finding one
+/// which differs from the solutions of the previous days.
+const ATTEMPTS: u64 = 100;
+
+/// The number of days with a distinct solution, enough for a leap year.
+const DAYS: usize = 366;
+
+/// A calendar date in the proleptic gregorian calendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    pub fn new(year: u32, month: u32, day: u32) -> result::Result<Date, String> {
+        if !(1..=12).contains(&month) {
+            return Err("The month has to be between 1 and 12.".to_string());
+        }
+        if !(1..=Self::days(year, month)).contains(&day) {
+            return Err(format!(
+                "The day has to be between 1 and {}.",
+                Self::days(year, month)
+            ));
+        }
+        return Ok(Date { year, month, day });
+    }
+
+    /// Return the number of the day within its year, starting at zero.
+    pub fn ordinal(&self) -> usize {
+        let before: u32 = (1..self.month).map(|m| Self::days(self.year, m)).sum();
+        return (before + self.day - 1) as usize;
+    }
+
+    fn leap(year: u32) -> bool {
+        (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+    }
+
+    /// Return the number of days in the given month.
+    fn days(year: u32, month: u32) -> u32 {
+        match month {
+            2 if Self::leap(year) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+/// Generate the puzzle of the day for the given date, sizes and level. The
+/// puzzle only depends on these arguments, and the puzzles of the days within
+/// the same year never have the same solution, not even after rotating,
+/// mirroring or swapping the symbols. Sizes without a distinct solution for
+/// each of the 366 days, such as 6x6, are rejected for every date.
+pub fn daily(
+    date: &Date,
+    width: usize,
+    height: usize,
+    level: level::Level,
+) -> result::Result<puzzle::Puzzle, String> {
+    daily_with(date, width, height, &generator::Options::new(level))
+}
+
+/// Generate the puzzle of the day for the given date and sizes which adheres to
+/// the given options.
+pub fn daily_with(
+    date: &Date,
+    width: usize,
+    height: usize,
+    options: &generator::Options,
+) -> result::Result<puzzle::Puzzle, String> {
+    let seed = seed(date.year, width, height, options.level);
+    let solutions = year(seed, width, height, &options.rules)?;
+    return generator::gen_from(
+        &solutions[date.ordinal()],
+        options,
+        Some(batch::derive(seed, date.ordinal() as u64)),
+    );
+}
+
+/// Return the base seed of all the puzzles of a year.
+fn seed(year: u32, width: usize, height: usize, level: level::Level) -> u64 {
+    // fixed numbers, such that the puzzles do not change when the levels do
+    let level = match level {
+        level::Level::Easy => 0,
+        level::Level::Medium => 1,
+        level::Level::Hard => 2,
+        level::Level::Inhuman => 3,
+    };
+    let seed = batch::derive(year as u64, width as u64);
+    return batch::derive(batch::derive(seed, height as u64), level);
+}
+
+/// Return the solutions of all the days of a year. They are generated once per
+/// seed, sizes and rules, and cached for all later calls.
+fn year(
+    seed: u64,
+    width: usize,
+    height: usize,
+    rules: &rules::Rules,
+) -> result::Result<Arc<Vec<puzzle::Puzzle>>, String> {
+    type Key = (u64, usize, usize, rules::Rules);
+    type Year = result::Result<Arc<Vec<puzzle::Puzzle>>, String>;
+    static CACHE: OnceLock<Mutex<HashMap<Key, Year>>> = OnceLock::new();
+
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut cache = cache.lock().unwrap();
+
+    return cache
+        .entry((seed, width, height, *rules))
+        .or_insert_with(|| solutions(seed, width, height, rules).map(Arc::new))
+        .clone();
+}
+
+/// Generate the solutions of all the days of a year, where every solution
+/// differs from the previous ones in canonical form. Generating solutions does
+/// not need the solver, so this takes well under a second for small sizes.
+fn solutions(
+    seed: u64,
+    width: usize,
+    height: usize,
+    rules: &rules::Rules,
+) -> result::Result<Vec<puzzle::Puzzle>, String> {
+    let blank = puzzle::Puzzle::new_with(width, height, *rules)?;
+    let mut seen = HashSet::new();
+    let mut solutions = Vec::with_capacity(DAYS);
+
+    for day in 0..DAYS {
+        let seed = batch::derive(seed, day as u64);
+        let solution = (0..ATTEMPTS).find_map(|attempt| {
+            let mut rng = generator::Rng::seed_from_u64(batch::derive(seed, attempt));
+            let solution = generator::init(&blank, &mut rng).ok()?;
+            seen.insert(solution.canonical()).then_some(solution)
+        });
+
+        match solution {
+            Some(solution) => solutions.push(solution),
+            None => {
+                return Err(format!(
+                    "Could not find a distinct solution with width {} and height {} for every day of a year.",
+                    width, height
+                ))
+            }
+        }
+    }
+    return Ok(solutions);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver;
+
+    #[test]
+    fn dates() {
+        assert_eq!(Date::new(2024, 1, 1).unwrap().ordinal(), 0);
+        assert_eq!(Date::new(2024, 3, 1).unwrap().ordinal(), 60);
+        assert_eq!(Date::new(2023, 3, 1).unwrap().ordinal(), 59);
+        assert_eq!(Date::new(2023, 12, 31).unwrap().ordinal(), 364);
+        assert!(Date::new(2023, 2, 29).is_err());
+        assert!(Date::new(2000, 2, 29).is_ok());
+        assert!(Date::new(1900, 2, 29).is_err());
+        assert!(Date::new(2024, 13, 1).is_err());
+        assert!(Date::new(2024, 4, 0).is_err());
+    }
+
+    #[test]
+    fn distinct_year() {
+        let seed = seed(2024, 8, 8, level::Level::Easy);
+        let solutions = solutions(seed, 8, 8, &rules::Rules::default()).unwrap();
+        let distinct: HashSet<_> = solutions.iter().map(|s| s.canonical()).collect();
+        assert_eq!(distinct.len(), DAYS);
+        assert_eq!(
+            solutions,
+            *year(seed, 8, 8, &rules::Rules::default()).unwrap()
+        );
+
+        // there are only 321 distinct 6x6 solutions, so even the first day fails
+        let date = Date::new(2024, 1, 1).unwrap();
+        assert!(daily(&date, 6, 6, level::Level::Easy).is_err());
+    }
+
+    #[test]
+    fn deterministic() {
+        let date = Date::new(2024, 2, 29).unwrap();
+        let puzzle = daily(&date, 8, 8, level::Level::Easy).unwrap();
+        assert_eq!(puzzle, daily(&date, 8, 8, level::Level::Easy).unwrap());
+        assert_eq!(level::Level::from(&puzzle), level::Level::Easy);
+        assert_eq!(solver::unique(&puzzle), Some(true));
+
+        let solution = solver::solve(&puzzle).unwrap();
+        let seed = seed(2024, 8, 8, level::Level::Easy);
+        assert_eq!(
+            solution,
+            solutions(seed, 8, 8, &rules::Rules::default()).unwrap()[59]
+        );
+    }
+}
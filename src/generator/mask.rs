@@ -0,0 +1,98 @@
+use std::result;
+
+/// What the generator is allowed to do with a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Free,    // the value can be given or not
+    Given,   // the value has to be given
+    Empty,   // the value is not allowed to be given
+    Blocked, // the cell is not part of the puzzle
+}
+
+/// A mask over the puzzle which determines which cells have to be given and
+/// which have to be empty, e.g. to spell out a shape with the given values, and
+/// which cells are blocked to give the grid a shape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask(Vec<Vec<Cell>>);
+
+impl Mask {
+    /// Create a new mask with the given sizes where every cell is free.
+    pub fn new(width: usize, height: usize) -> Self {
+        Mask(vec![vec![Cell::Free; width]; height])
+    }
+
+    pub fn width(&self) -> usize {
+        self[0].len()
+    }
+
+    pub fn height(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Create a mask from a pattern with one character per cell, row by row. A
+    /// '#' is a given cell, a '.' is an empty cell, a '?' is a free cell and a
+    /// '_' is a blocked cell. All whitespace is ignored.
+    pub fn from_pattern(
+        pattern: &str,
+        width: usize,
+        height: usize,
+    ) -> result::Result<Mask, String> {
+        let cells = pattern
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| match c {
+                '?' => Ok(Cell::Free),
+                '#' => Ok(Cell::Given),
+                '.' => Ok(Cell::Empty),
+                '_' => Ok(Cell::Blocked),
+                _ => Err("Make sure the pattern only contains '?', '#', '.' and '_'.".to_string()),
+            })
+            .collect::<result::Result<Vec<_>, _>>()?;
+
+        if width == 0 || cells.len() != width * height {
+            return Err("The size of the pattern is invalid.".to_string());
+        }
+
+        return Ok(Mask(cells.chunks(width).map(|row| row.to_vec()).collect()));
+    }
+}
+
+impl std::ops::Index<usize> for Mask {
+    type Output = Vec<Cell>;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        return &self.0[index];
+    }
+}
+
+impl std::ops::IndexMut<usize> for Mask {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        return &mut self.0[index];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern() {
+        let mask = Mask::from_pattern("#??. ???? ???? .??#", 4, 4).unwrap();
+        assert_eq!(mask.width(), 4);
+        assert_eq!(mask.height(), 4);
+        assert_eq!(mask[0][0], Cell::Given);
+        assert_eq!(mask[0][3], Cell::Empty);
+        assert_eq!(mask[1][1], Cell::Free);
+        assert_eq!(mask[3][3], Cell::Given);
+
+        let mask = Mask::from_pattern("?_?? ????", 4, 2).unwrap();
+        assert_eq!(mask[0][1], Cell::Blocked);
+    }
+
+    #[test]
+    fn pattern_error() {
+        assert!(Mask::from_pattern("#??.", 4, 4).is_err());
+        assert!(Mask::from_pattern("#??x", 2, 2).is_err());
+        assert!(Mask::from_pattern("", 0, 4).is_err());
+    }
+}
@@ -1,71 +1,813 @@
-use crate::{analyzer::level, puzzle, solver};
+use crate::{
+    analyzer::{
+        self, level,
+        tactics::{self, line},
+    },
+    puzzle::{self, marker, rules},
+    solver,
+};
 use itertools::Itertools;
-use rand::{self, seq::SliceRandom, Rng, SeedableRng};
+use rand::{self, seq::SliceRandom, Rng as _, SeedableRng};
+use std::collections::VecDeque;
 use std::result;
 
-/// Generate a puzzle with the given sizes and from the given seed, if any.
+pub mod batch;
+pub mod daily;
+pub mod mask;
+pub mod requirement;
+pub mod symmetry;
+
+/// The random generator used for generating puzzles, which gives the same
+/// numbers from the same seed on every platform.
+type Rng = rand_chacha::ChaCha8Rng;
+
+/// The number of puzzles tried before giving up on reaching the level.
+const ATTEMPTS: usize = 25;
+
+/// The number of times the local search swaps given values for empty cells.
+const SWAPS: usize = 50;
+
+/// Everything the generated puzzles have to adhere to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub level: level::Level,
+    pub requirements: Vec<requirement::Requirement>,
+    pub symmetry: symmetry::Symmetry,
+    pub givens: Option<usize>, // the maximum number of given values
+    pub minimal: bool,         // whether every given value should be required
+    pub mask: Option<mask::Mask>,
+    pub rules: rules::Rules, // only used when no solution is given
+    pub markers: bool,       // whether given values can be replaced by markers
+    pub clues: bool,         // whether lines can get a clue with their number of ones
+    pub attempts: usize,
+}
+
+impl Options {
+    pub fn new(level: level::Level) -> Self {
+        Options {
+            level,
+            requirements: Vec::new(),
+            symmetry: symmetry::Symmetry::None,
+            givens: None,
+            minimal: false,
+            mask: None,
+            rules: rules::Rules::default(),
+            markers: false,
+            clues: false,
+            attempts: ATTEMPTS,
+        }
+    }
+
+    /// Check whether removing more values can still lead to a valid puzzle.
+    fn allows(&self, stats: &analyzer::Stats) -> bool {
+        level::Level::from_stats(stats) <= self.level
+            && self.requirements.iter().all(|r| r.allows(stats))
+    }
+
+    /// Check whether the puzzle adheres to all of the options.
+    fn satisfied(&self, gen: &puzzle::Puzzle) -> bool {
+        let stats = analyzer::Stats::from(gen);
+
+        level::Level::from_stats(&stats) == self.level
+            && self.requirements.iter().all(|r| r.satisfied(&stats))
+            && self.sparse(gen)
+    }
+
+    /// Check whether the puzzle does not have too many given values.
+    fn sparse(&self, gen: &puzzle::Puzzle) -> bool {
+        self.givens.is_none_or(|givens| gen.givens() <= givens)
+            && (!self.minimal || solver::is_minimal(gen))
+    }
+}
+
+/// Generate a puzzle with the given sizes and from the given seed, if any. The
+/// puzzle is of exactly the given level, if no such puzzle is found within a
+/// number of attempts an error is returned.
 pub fn gen(
     width: usize,
     height: usize,
     level: level::Level,
     seed: Option<u64>,
+) -> result::Result<puzzle::Puzzle, String> {
+    gen_with(width, height, &Options::new(level), seed)
+}
+
+/// Generate a puzzle with the given sizes which adheres to the given options,
+/// from the given seed, if any.
+pub fn gen_with(
+    width: usize,
+    height: usize,
+    options: &Options,
+    seed: Option<u64>,
+) -> result::Result<puzzle::Puzzle, String> {
+    generate(width, height, None, options, seed)
+}
+
+/// Generate a puzzle with the given solution which adheres to the given options,
+/// from the given seed, if any.
+pub fn gen_from(
+    solution: &puzzle::Puzzle,
+    options: &Options,
+    seed: Option<u64>,
+) -> result::Result<puzzle::Puzzle, String> {
+    if solution.givens() != solution.size() || solver::unique(solution) != Some(true) {
+        return Err(
+            "The solution has to be a completely and validly filled in puzzle.".to_string(),
+        );
+    }
+    generate(
+        solution.width(),
+        solution.height(),
+        Some(solution),
+        options,
+        seed,
+    )
+}
+
+/// Generate a puzzle from the given solution, or from random solutions if none
+/// is given, until the puzzle adheres to all the options.
+fn generate(
+    width: usize,
+    height: usize,
+    solution: Option<&puzzle::Puzzle>,
+    options: &Options,
+    seed: Option<u64>,
 ) -> result::Result<puzzle::Puzzle, String> {
     // use the seed if given
     let mut rng = {
         if seed.is_none() {
-            rand::rngs::SmallRng::from_rng(rand::thread_rng()).unwrap()
+            Rng::from_rng(rand::thread_rng()).unwrap()
         } else {
-            rand::rngs::SmallRng::seed_from_u64(seed.unwrap())
+            Rng::seed_from_u64(seed.unwrap())
         }
     };
 
-    // generate a puzzle
-    let mut gen = init(width, height, &mut rng)?;
-    eliminate(&mut gen, level, &mut rng);
+    // the cells which are blocked in the mask are blocked in the solution
+    let mut blank = puzzle::Puzzle::new_with(width, height, options.rules)?;
+    if options.clues && solution.map_or(options.rules, |s| *s.rules()).symbols != 2 {
+        return Err("Only puzzles with two symbols can have clues.".to_string());
+    }
+    if let Some(mask) = &options.mask {
+        if mask.width() != width || mask.height() != height {
+            return Err("The mask should have the same size as the puzzle.".to_string());
+        }
+        for y in 0..height {
+            for x in 0..width {
+                blank.set_blocked(x, y, mask[y][x] == mask::Cell::Blocked);
+            }
+        }
+    }
+    if let Some(solution) = solution {
+        let blocked = |gen: &puzzle::Puzzle| {
+            (0..height)
+                .cartesian_product(0..width)
+                .map(|(y, x)| gen.blocked(x, y))
+                .collect::<Vec<_>>()
+        };
+        if options.mask.is_some() && blocked(solution) != blocked(&blank) {
+            return Err("The mask should block the same cells as the solution.".to_string());
+        }
+        blank = solution.clone();
+    }
 
-    return Ok(gen);
-}
+    // only the groups of free cells can be eliminated
+    let mut groups = options.symmetry.groups(width, height)?;
+    groups.retain(|group| group.iter().all(|&(y, x)| !blank.blocked(x, y)));
+    if let Some(mask) = &options.mask {
+        groups.retain(|group| group.iter().all(|&(y, x)| mask[y][x] == mask::Cell::Free));
+    }
 
-/// Generate a random solved puzzle of the given size.
-fn init(
-    width: usize,
-    height: usize,
-    rng: &mut rand::rngs::SmallRng,
-) -> result::Result<puzzle::Puzzle, String> {
-    let mut gen = puzzle::Puzzle::new(width, height)?;
+    // generate puzzles until one adheres to all the options
+    for _ in 0..options.attempts {
+        let solution = match solution {
+            Some(solution) => solution.clone(),
+            None => init(&blank, &mut rng)?,
+        };
+        let mut gen = solution.clone();
+
+        // leave out the values which are not allowed to be given
+        if let Some(mask) = &options.mask {
+            for y in 0..height {
+                for x in 0..width {
+                    if mask[y][x] == mask::Cell::Empty {
+                        gen[y][x] = None;
+                    }
+                }
+            }
+            if !allowed(&gen, options) {
+                continue;
+            }
+        }
+
+        // with all the markers in place most values can be eliminated, after
+        // which the markers which are not required are eliminated as well
+        if options.markers {
+            for (x, y, direction) in gen.edges() {
+                let (nx, ny) = direction.neighbour(x, y);
+                if let (Some(a), Some(b)) = (solution[y][x], solution[ny][nx]) {
+                    gen.set_marker(x, y, direction, Some(marker::Marker::between(a, b)));
+                }
+            }
+        }
+
+        // the clues of all the lines are given at first as well
+        if options.clues {
+            for l in puzzle::line::all(&gen) {
+                let ones = l
+                    .values(&solution)
+                    .iter()
+                    .filter(|&&v| v == Some(1))
+                    .count();
+                l.set_clue(&mut gen, Some(ones));
+            }
+        }
+
+        eliminate(&mut gen, &groups, options, &mut rng);
+        if options.markers {
+            unmark(&mut gen, options, &mut rng);
+        }
+        if options.clues {
+            unclue(&mut gen, options, &mut rng);
+        }
+
+        if options.givens.is_some() || options.minimal {
+            search(&mut gen, &solution, &groups, options, &mut rng);
+        }
 
-    // fill the puzzle with a few numbers
-    for symbol in [0, 0, 1, 1] {
-        let y = rng.gen_range(0..height);
-        let x = rng.gen_range(0..width);
-        gen[y][x] = Some(symbol);
+        if options.satisfied(&gen) {
+            return Ok(gen);
+        }
     }
 
-    // solve the puzzle (it is always solvable)
-    gen = solver::solve(&gen).expect(&format!(
-        "It is not possible to generate a puzzle with width {} and height {}.",
-        width, height
+    return Err(format!(
+        "Could not generate a puzzle of level {:?} with width {} and height {} within {} attempts.",
+        options.level, width, height, options.attempts
     ));
+}
 
-    return Ok(gen);
+/// Generate a random solution of the given empty puzzle. Instead of asking a
+/// solver for any model, random values are filled in with backtracking, such
+/// that the same random generator always gives the same solution.
+fn init(blank: &puzzle::Puzzle, rng: &mut Rng) -> result::Result<puzzle::Puzzle, String> {
+    let (width, height) = (blank.width(), blank.height());
+    let lines = VecDeque::from(puzzle::line::all(blank));
+
+    // start over with a larger budget of failed guesses when the search gets
+    // stuck, as a few unlucky early guesses can take very long to undo
+    let mut budget = blank.size();
+    loop {
+        let mut gen = blank.clone();
+        let mut left = budget;
+        if fill(&mut gen, lines.clone(), rng, &mut left) {
+            return Ok(gen);
+        }
+        if left > 0 {
+            return Err(format!(
+                "It is not possible to generate a puzzle with width {} and height {}.",
+                width, height
+            ));
+        }
+        budget *= 2;
+    }
 }
 
-/// Eliminate all the values which are not required for a unique solution.
-fn eliminate(gen: &mut puzzle::Puzzle, level: level::Level, mut rng: &mut rand::rngs::SmallRng) {
-    // shuffle the order in which all the cells are visited
-    let mut cells: Vec<_> = (0..gen.height())
+/// Fill in the cells forced by the given lines, and then guess a random value
+/// for a random empty cell. Return whether the puzzle could be filled in, if not
+/// the puzzle is left unchanged. Every guess which turns out to be wrong uses up
+/// the budget, when it runs out the search is given up.
+fn fill(
+    gen: &mut puzzle::Puzzle,
+    lines: VecDeque<line::Line>,
+    rng: &mut Rng,
+    budget: &mut usize,
+) -> bool {
+    let before = gen.clone();
+    if !propagate(gen, lines) {
+        *gen = before;
+        *budget = budget.saturating_sub(1);
+        return false;
+    }
+
+    let empty: Vec<_> = (0..gen.height())
         .cartesian_product(0..gen.width())
+        .filter(|&(y, x)| gen[y][x].is_none() && !gen.blocked(x, y))
         .collect();
-    cells.shuffle(&mut rng);
+    if empty.is_empty() {
+        return true;
+    }
+
+    let &(y, x) = empty.choose(rng).unwrap();
+    let symbols = gen.rules().symbols;
+    let guess = rng.gen_range(0..symbols);
+    for v in (0..symbols).map(|s| (guess + s) % symbols) {
+        gen[y][x] = Some(v);
+        if fill(gen, VecDeque::from(line::through(gen, x, y)), rng, budget) {
+            return true;
+        }
+        if *budget == 0 {
+            break;
+        }
+    }
+
+    *gen = before;
+    return false;
+}
+
+/// Fill in every cell which has the same value in all the valid completions of
+/// its lines, until no more cells are forced. Return false if one of the lines
+/// can not be completed anymore.
+fn propagate(gen: &mut puzzle::Puzzle, mut queue: VecDeque<line::Line>) -> bool {
+    while let Some(l) = queue.pop_front() {
+        let rules = l.rules(gen);
+        let parallel = l.parallel(gen);
+
+        // a line can not be equal to one of the full lines in the same direction
+        // with the same blocked cells, if the rules ask for distinct lines
+        let blocked = l.blocked(gen);
+        let exclude: Vec<_> = parallel
+            .iter()
+            .copied()
+            .filter(|&other| other != l && rules.distinct)
+            .filter(|&other| other.full(gen) && other.blocked(gen) == blocked)
+            .map(|other| line::pattern(&other.values(gen), rules.symbols))
+            .collect();
+
+        let values = l.values(gen);
+        let counts = line::count(
+            &values,
+            &blocked,
+            &l.markers(gen),
+            l.clue(gen),
+            &exclude,
+            &rules,
+        );
+        if counts.total == 0 {
+            return false;
+        }
+
+        for (i, value) in values.iter().enumerate() {
+            if value.is_none() && !blocked[i] {
+                let mut possible = (0..rules.symbols).filter(|&v| counts.cells[i][v as usize] > 0);
+                let v = match (possible.next(), possible.next()) {
+                    (Some(v), None) => v,
+                    _ => continue,
+                };
+                l.set(gen, i, Some(v));
+                let (x, y) = l.position(i);
+                for other in line::through(gen, x, y) {
+                    if other != l && !queue.contains(&other) {
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+
+        // a full line changes what the other lines in the same direction can be
+        if counts.total == 1 && rules.distinct {
+            for other in parallel {
+                if !other.full(gen) && !queue.contains(&other) {
+                    queue.push_back(other);
+                }
+            }
+        }
+    }
+    return true;
+}
+
+/// Eliminate all the values which are not required for a unique solution.
+fn eliminate(
+    gen: &mut puzzle::Puzzle,
+    groups: &[Vec<(usize, usize)>],
+    options: &Options,
+    mut rng: &mut Rng,
+) {
+    // shuffle the order in which all the groups of symmetric cells are visited
+    let mut groups = groups.to_vec();
+    groups.shuffle(&mut rng);
+
+    // keep the values only if the solution is not unique upon removal
+    for group in groups {
+        if group.iter().all(|&(y, x)| gen[y][x].is_none()) {
+            continue;
+        }
+
+        let symbols: Vec<_> = group.iter().map(|&(y, x)| gen[y][x]).collect();
+        for &(y, x) in &group {
+            gen[y][x] = None;
+        }
+
+        if !allowed(gen, options) {
+            for (&(y, x), symbol) in group.iter().zip(symbols) {
+                gen[y][x] = symbol;
+            }
+        }
+    }
+}
 
-    // keep a value only if the solution is not unique upon removal
-    for (y, x) in cells {
-        let symbol = gen[y][x];
-        gen[y][x] = None;
+/// Eliminate all the markers which are not required for a unique solution.
+fn unmark(gen: &mut puzzle::Puzzle, options: &Options, mut rng: &mut Rng) {
+    let mut edges = gen.edges();
+    edges.shuffle(&mut rng);
 
-        // check if the solution is still unique and the appropriate level
-        if solver::unique(&gen) != Some(true) || level::Level::from(&gen) > level {
-            gen[y][x] = symbol;
+    for (x, y, direction) in edges {
+        let marker = gen.marker(x, y, direction);
+        if marker.is_none() {
+            continue;
         }
+
+        gen.set_marker(x, y, direction, None);
+        if !allowed(gen, options) {
+            gen.set_marker(x, y, direction, marker);
+        }
+    }
+}
+
+/// Eliminate all the clues which are not required for a unique solution.
+fn unclue(gen: &mut puzzle::Puzzle, options: &Options, mut rng: &mut Rng) {
+    let mut lines = puzzle::line::all(gen);
+    lines.shuffle(&mut rng);
+
+    for l in lines {
+        let clue = l.clue(gen);
+        if clue.is_none() {
+            continue;
+        }
+
+        l.set_clue(gen, None);
+        if !allowed(gen, options) {
+            l.set_clue(gen, clue);
+        }
+    }
+}
+
+/// Search for a puzzle with fewer given values, by putting back the values of
+/// a random empty group and removing a random given group. When this is allowed
+/// the values are eliminated again, and the new puzzle is kept if it does not
+/// have more given values.
+fn search(
+    gen: &mut puzzle::Puzzle,
+    solution: &puzzle::Puzzle,
+    groups: &[Vec<(usize, usize)>],
+    options: &Options,
+    mut rng: &mut Rng,
+) {
+    for _ in 0..SWAPS {
+        if options.sparse(gen) {
+            return;
+        }
+
+        let (given, empty): (Vec<_>, Vec<_>) = groups
+            .iter()
+            .partition(|group| gen[group[0].0][group[0].1].is_some());
+        if given.is_empty() || empty.is_empty() {
+            return;
+        }
+
+        // swap a given group with an empty group
+        let mut next = gen.clone();
+        for &(y, x) in *empty.choose(&mut rng).unwrap() {
+            next[y][x] = solution[y][x];
+        }
+        for &(y, x) in *given.choose(&mut rng).unwrap() {
+            next[y][x] = None;
+        }
+
+        if allowed(&next, options) {
+            eliminate(&mut next, groups, options, rng);
+            if next.givens() <= gen.givens() {
+                *gen = next;
+            }
+        }
+    }
+}
+
+/// Check whether the solution is still unique and the options are not violated.
+fn allowed(gen: &puzzle::Puzzle, options: &Options) -> bool {
+    let solved = |stats: &analyzer::Stats| stats.solved.givens() == gen.size();
+
+    // below the hard level the tactics of the level have to solve the puzzle,
+    // which is checked first as it avoids backtracking with the solver
+    if options.level < level::Level::Hard
+        && !solved(&analyzer::Stats::from_level(gen, options.level))
+    {
+        return false;
+    }
+
+    let stats = analyzer::Stats::from(gen);
+    if !options.allows(&stats) {
+        return false;
+    }
+
+    // all tactics but backtracking only fill in forced values, so the solution
+    // is unique if they solve the puzzle without asking the solver
+    if stats.count(tactics::Tactics::Backtrack) == Ok(0) && solved(&stats) {
+        return true;
+    }
+    return solver::unique(gen) == Some(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::tactics;
+    use std::time;
+
+    #[test]
+    fn exact_level() {
+        for level in [level::Level::Easy, level::Level::Medium] {
+            let gen = gen(8, 8, level, Some(0)).unwrap();
+            assert_eq!(level::Level::from(&gen), level);
+            assert_eq!(solver::unique(&gen), Some(true));
+        }
+    }
+
+    #[test]
+    fn requirements() {
+        let mut options = Options::new(level::Level::Medium);
+        options.requirements = vec![
+            requirement::Requirement::at_least(tactics::Tactics::Uniqueness, 2),
+            requirement::Requirement::never(tactics::Tactics::CountGuess),
+        ];
+
+        let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+        let stats = analyzer::Stats::from(&gen);
+        assert!(stats.count(tactics::Tactics::Uniqueness).unwrap() >= 2);
+        assert_eq!(stats.count(tactics::Tactics::CountGuess), Ok(0));
+        assert_eq!(level::Level::from(&gen), level::Level::Medium);
+    }
+
+    #[test]
+    fn symmetric() {
+        let mut options = Options::new(level::Level::Easy);
+        for symmetry in [
+            symmetry::Symmetry::Rotational,
+            symmetry::Symmetry::Horizontal,
+            symmetry::Symmetry::Vertical,
+            symmetry::Symmetry::Diagonal,
+        ] {
+            options.symmetry = symmetry;
+            let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+            assert_eq!(solver::unique(&gen), Some(true));
+            assert_eq!(level::Level::from(&gen), level::Level::Easy);
+
+            for y in 0..6 {
+                for x in 0..6 {
+                    let (ix, iy) = symmetry.image(x, y, 6, 6);
+                    assert_eq!(gen[y][x].is_some(), gen[iy][ix].is_some());
+                }
+            }
+        }
+
+        options.symmetry = symmetry::Symmetry::Diagonal;
+        assert!(gen_with(6, 4, &options, Some(0)).is_err());
+    }
+
+    #[test]
+    fn sparse() {
+        let mut options = Options::new(level::Level::Easy);
+        options.givens = Some(8);
+        let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+        assert!(gen.givens() <= 8);
+        assert_eq!(level::Level::from(&gen), level::Level::Easy);
+
+        let mut options = Options::new(level::Level::Easy);
+        options.minimal = true;
+        let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+        assert!(solver::is_minimal(&gen));
+        assert_eq!(level::Level::from(&gen), level::Level::Easy);
+    }
+
+    #[test]
+    fn from_solution() {
+        let solution =
+            puzzle::Puzzle::from_codex("011001110010101100001011010110100101", 6, 6).unwrap();
+        let gen = gen_from(&solution, &Options::new(level::Level::Easy), Some(0)).unwrap();
+        assert_eq!(solver::solve(&gen).as_ref(), Some(&solution));
+        assert_eq!(level::Level::from(&gen), level::Level::Easy);
+
+        let invalid = puzzle::Puzzle::from_codex("011001110010101100001011010110100100", 6, 6);
+        assert!(gen_from(&invalid.unwrap(), &Options::new(level::Level::Easy), None).is_err());
+        let mut partial = solution.clone();
+        partial[5][5] = None;
+        assert!(gen_from(&partial, &Options::new(level::Level::Easy), None).is_err());
+    }
+
+    #[test]
+    fn masked() {
+        let mut options = Options::new(level::Level::Easy);
+        let pattern = "#????# ?....? ?????? ?????? ?....? #????#";
+        options.mask = Some(mask::Mask::from_pattern(pattern, 6, 6).unwrap());
+
+        let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+        let mask = options.mask.unwrap();
+        for y in 0..6 {
+            for x in 0..6 {
+                match mask[y][x] {
+                    mask::Cell::Given => assert!(gen[y][x].is_some()),
+                    mask::Cell::Empty => assert!(gen[y][x].is_none()),
+                    mask::Cell::Free => (),
+                    mask::Cell::Blocked => assert!(gen.blocked(x, y)),
+                }
+            }
+        }
+        assert_eq!(solver::unique(&gen), Some(true));
+
+        options.mask = Some(mask::Mask::new(4, 4));
+        assert!(gen_with(6, 6, &options, Some(0)).is_err());
+    }
+
+    #[test]
+    fn random_solutions() {
+        let mut rng = Rng::seed_from_u64(0);
+        for (width, height) in [
+            (4, 4),
+            (6, 4),
+            (4, 6),
+            (10, 10),
+            (14, 8),
+            (20, 20),
+            (30, 30),
+            (30, 8),
+            (8, 30),
+            (28, 14),
+        ] {
+            let solution = init(&puzzle::Puzzle::new(width, height).unwrap(), &mut rng).unwrap();
+            assert_eq!(solution.givens(), width * height);
+            assert_eq!(solver::unique(&solution), Some(true));
+        }
+
+        // there are only six valid lines of length four
+        assert!(init(&puzzle::Puzzle::new(4, 8).unwrap(), &mut rng).is_err());
+        assert!(init(&puzzle::Puzzle::new(8, 4).unwrap(), &mut rng).is_err());
+    }
+
+    #[test]
+    fn large() {
+        for (width, height) in [(20, 20), (30, 12)] {
+            let gen = gen(width, height, level::Level::Easy, Some(0)).unwrap();
+            assert_eq!(gen.width(), width);
+            assert_eq!(gen.height(), height);
+            assert_eq!(level::Level::from(&gen), level::Level::Easy);
+            assert_eq!(solver::unique(&gen), Some(true));
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn large_time() {
+        // the target for a large easy puzzle depends on the machine, so it only
+        // runs with --ignored and leaves room for unoptimized builds
+        let target = match cfg!(debug_assertions) {
+            true => time::Duration::from_secs(60),
+            false => time::Duration::from_secs(10),
+        };
+        for (width, height) in [(20, 20), (30, 12)] {
+            let start = time::Instant::now();
+            gen(width, height, level::Level::Easy, Some(0)).unwrap();
+            assert!(start.elapsed() < target);
+        }
+    }
+
+    #[test]
+    fn relaxed() {
+        let mut options = Options::new(level::Level::Easy);
+        options.rules = rules::Rules::relaxed();
+        for (width, height) in [(7, 7), (9, 5)] {
+            let gen = gen_with(width, height, &options, Some(0)).unwrap();
+            assert_eq!(gen.rules(), &rules::Rules::relaxed());
+            assert_eq!(level::Level::from(&gen), level::Level::Easy);
+            assert_eq!(solver::unique(&gen), Some(true));
+        }
+        assert!(gen(7, 7, level::Level::Easy, Some(0)).is_err());
+    }
+
+    #[test]
+    fn unruly() {
+        let mut options = Options::new(level::Level::Medium);
+        options.rules = rules::Rules::unruly();
+        let gen = gen_with(8, 8, &options, Some(0)).unwrap();
+        assert_eq!(gen.rules(), &rules::Rules::unruly());
+        assert_eq!(level::Level::from(&gen), level::Level::Medium);
+        assert_eq!(solver::unique(&gen), Some(true));
+    }
+
+    #[test]
+    fn markers() {
+        let mut options = Options::new(level::Level::Easy);
+        options.markers = true;
+        let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+        assert!(gen.markers() > 0);
+        assert_eq!(level::Level::from(&gen), level::Level::Easy);
+        assert_eq!(solver::unique(&gen), Some(true));
+    }
+
+    #[test]
+    fn runs() {
+        let mut options = Options::new(level::Level::Medium);
+        for longest in [3, 4] {
+            options.rules.run = Some(longest);
+            let gen = gen_with(8, 8, &options, Some(0)).unwrap();
+            assert_eq!(gen.rules().run, Some(longest));
+            assert_eq!(level::Level::from(&gen), level::Level::Medium);
+            assert_eq!(solver::unique(&gen), Some(true));
+        }
+    }
+
+    #[test]
+    fn toroidal() {
+        let mut options = Options::new(level::Level::Medium);
+        options.rules = rules::Rules::toroidal();
+        let gen = gen_with(8, 8, &options, Some(0)).unwrap();
+        assert!(gen.rules().wrap);
+        assert_eq!(level::Level::from(&gen), level::Level::Medium);
+        assert_eq!(solver::unique(&gen), Some(true));
+
+        // the same cells are a different puzzle under the standard rules
+        let standard = puzzle::Puzzle::from_codex(&gen.codex(), 8, 8).unwrap();
+        assert_ne!(standard, gen);
+        assert_ne!(standard.canonical_hash(), gen.canonical_hash());
+    }
+
+    #[test]
+    fn blocked() {
+        let mut options = Options::new(level::Level::Easy);
+        let pattern = "__???? __???? ?????? ?????? ?????? ??????";
+        options.mask = Some(mask::Mask::from_pattern(pattern, 6, 6).unwrap());
+        let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+        assert!(gen.blocked(0, 0) && gen.blocked(1, 1));
+        assert_eq!(gen.size(), 32);
+        assert_eq!(level::Level::from(&gen), level::Level::Easy);
+        assert_eq!(solver::unique(&gen), Some(true));
+
+        // the mask has to block the same cells as the given solution
+        let solution = solver::solve(&gen).unwrap();
+        assert!(gen_from(&solution, &options, Some(0)).is_ok());
+        options.mask = Some(mask::Mask::new(6, 6));
+        assert!(gen_from(&solution, &options, Some(0)).is_err());
+    }
+
+    #[test]
+    fn clues() {
+        let mut options = Options::new(level::Level::Easy);
+        options.rules = rules::Rules::clued();
+        options.clues = true;
+        let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+        assert!(gen.clues() > 0);
+        assert_eq!(level::Level::from(&gen), level::Level::Easy);
+        assert_eq!(solver::unique(&gen), Some(true));
+
+        // without the clues the puzzle is not unique
+        let mut plain = gen.clone();
+        for i in 0..6 {
+            plain.set_row_clue(i, None);
+            plain.set_column_clue(i, None);
+        }
+        assert_eq!(solver::unique(&plain), Some(false));
+    }
+
+    #[test]
+    fn trinairo() {
+        let mut options = Options::new(level::Level::Medium);
+        options.rules = rules::Rules::trinairo();
+        let gen = gen_with(6, 6, &options, Some(0)).unwrap();
+        assert_eq!(gen.rules().symbols, 3);
+        assert!((0..6).any(|y| gen[y].contains(&Some(2))));
+        options.clues = true;
+        assert!(gen_with(6, 6, &options, Some(0)).is_err());
+        assert_eq!(level::Level::from(&gen), level::Level::Medium);
+        assert_eq!(solver::unique(&gen), Some(true));
+    }
+
+    #[test]
+    fn diagonal() {
+        let mut options = Options::new(level::Level::Medium);
+        options.rules = rules::Rules::diagonal();
+        let gen = gen_with(8, 8, &options, Some(0)).unwrap();
+        assert_eq!(level::Level::from(&gen), level::Level::Medium);
+        assert_eq!(solver::unique(&gen), Some(true));
+        let solution = solver::solve(&gen).unwrap();
+        let ones = (0..8).filter(|&i| solution[i][i] == Some(1)).count();
+        assert_eq!(ones, 4);
+        assert!(gen_with(8, 6, &options, Some(0)).is_err());
+    }
+
+    #[test]
+    fn golden() {
+        // the same seed should always give the same puzzle
+        let gen = gen(8, 8, level::Level::Easy, Some(1)).unwrap();
+        assert_eq!(gen.codex(), "a11c0c0c0a1g0a0a1a11j01b00b1a0c1g");
+
+        let mut rng = Rng::seed_from_u64(7);
+        assert_eq!(
+            init(&puzzle::Puzzle::new(10, 10).unwrap(), &mut rng)
+                .unwrap()
+                .codex(),
+            "1001010101101001101001101001100101100101100101101000101100111101001100001101001101101010011100101100"
+        );
+    }
+
+    #[test]
+    fn unreachable_level() {
+        assert!(gen(4, 4, level::Level::Inhuman, Some(0)).is_err());
     }
 }
@@ -0,0 +1,75 @@
+use crate::analyzer::{self, tactics};
+
+/// A requirement on how many times a tactic has to be applied to solve the
+/// generated puzzle, as counted by the analyzer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Requirement {
+    pub tactic: tactics::Tactics,
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Requirement {
+    pub fn new(tactic: tactics::Tactics, min: usize, max: Option<usize>) -> Self {
+        Requirement { tactic, min, max }
+    }
+
+    /// The tactic has to be applied at least the given number of times.
+    pub fn at_least(tactic: tactics::Tactics, min: usize) -> Self {
+        Self::new(tactic, min, None)
+    }
+
+    /// The tactic can be applied at most the given number of times.
+    pub fn at_most(tactic: tactics::Tactics, max: usize) -> Self {
+        Self::new(tactic, 0, Some(max))
+    }
+
+    /// The tactic is not allowed to be applied at all.
+    pub fn never(tactic: tactics::Tactics) -> Self {
+        Self::at_most(tactic, 0)
+    }
+
+    /// Check whether the tactic is not applied more often than the maximum.
+    pub fn allows(&self, stats: &analyzer::Stats) -> bool {
+        match self.max {
+            Some(max) => self.count(stats) <= max,
+            None => true,
+        }
+    }
+
+    /// Check whether the number of times the tactic is applied is in range.
+    pub fn satisfied(&self, stats: &analyzer::Stats) -> bool {
+        self.count(stats) >= self.min && self.allows(stats)
+    }
+
+    fn count(&self, stats: &analyzer::Stats) -> usize {
+        stats
+            .count(self.tactic)
+            .expect("The analyzer should count all the tactics.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{puzzle, solver};
+
+    #[test]
+    fn bounds() {
+        // the puzzle has to be uniquely solvable, otherwise the counts depend
+        // on which solution the backtracking happens to find
+        let puzzle = puzzle::Puzzle::from_codex("a0d1g1a0a1h1b1a00b", 6, 6).unwrap();
+        assert_eq!(solver::unique(&puzzle), Some(true));
+        let stats = analyzer::Stats::from(&puzzle);
+        assert_eq!(stats.count(tactics::Tactics::Uniqueness), Ok(4));
+
+        assert!(Requirement::at_least(tactics::Tactics::Uniqueness, 4).satisfied(&stats));
+        assert!(!Requirement::at_least(tactics::Tactics::Uniqueness, 5).satisfied(&stats));
+        assert!(Requirement::at_most(tactics::Tactics::Uniqueness, 4).satisfied(&stats));
+        assert!(!Requirement::at_most(tactics::Tactics::Uniqueness, 3).allows(&stats));
+        assert!(!Requirement::never(tactics::Tactics::Uniqueness).allows(&stats));
+        assert!(!Requirement::never(tactics::Tactics::Backtrack).allows(&stats));
+        assert!(Requirement::new(tactics::Tactics::CountGuess, 2, Some(2)).satisfied(&stats));
+        assert!(!Requirement::new(tactics::Tactics::Row2, 0, Some(2)).satisfied(&stats));
+    }
+}
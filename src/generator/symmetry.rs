@@ -0,0 +1,73 @@
+use std::result;
+
+/// The symmetry of the pattern formed by the given values of a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    Rotational, // rotating the puzzle by 180 degrees
+    Horizontal, // mirroring the top and bottom half
+    Vertical,   // mirroring the left and right half
+    Diagonal,   // mirroring in the main diagonal, only for square puzzles
+}
+
+impl Symmetry {
+    /// Return the cell which the given cell is mapped to by this symmetry.
+    pub fn image(&self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Symmetry::None => (x, y),
+            Symmetry::Rotational => (width - 1 - x, height - 1 - y),
+            Symmetry::Horizontal => (x, height - 1 - y),
+            Symmetry::Vertical => (width - 1 - x, y),
+            Symmetry::Diagonal => (y, x),
+        }
+    }
+
+    /// Split all the cells (y, x) into groups of cells which are mapped onto
+    /// each other, these groups are either all given or all empty.
+    pub fn groups(
+        &self,
+        width: usize,
+        height: usize,
+    ) -> result::Result<Vec<Vec<(usize, usize)>>, String> {
+        if *self == Symmetry::Diagonal && width != height {
+            return Err("Diagonal symmetry is only possible for square puzzles.".to_string());
+        }
+
+        let mut groups = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let (ix, iy) = self.image(x, y, width, height);
+                if (iy, ix) == (y, x) {
+                    groups.push(vec![(y, x)]);
+                } else if (y, x) < (iy, ix) {
+                    groups.push(vec![(y, x), (iy, ix)]);
+                }
+            }
+        }
+        return Ok(groups);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images() {
+        assert_eq!(Symmetry::None.image(1, 2, 6, 4), (1, 2));
+        assert_eq!(Symmetry::Rotational.image(1, 2, 6, 4), (4, 1));
+        assert_eq!(Symmetry::Horizontal.image(1, 2, 6, 4), (1, 1));
+        assert_eq!(Symmetry::Vertical.image(1, 2, 6, 4), (4, 2));
+        assert_eq!(Symmetry::Diagonal.image(1, 2, 4, 4), (2, 1));
+    }
+
+    #[test]
+    fn group_sizes() {
+        assert_eq!(Symmetry::None.groups(4, 4).unwrap().len(), 16);
+        assert_eq!(Symmetry::Rotational.groups(6, 4).unwrap().len(), 12);
+        assert_eq!(Symmetry::Horizontal.groups(6, 4).unwrap().len(), 12);
+        assert_eq!(Symmetry::Vertical.groups(6, 4).unwrap().len(), 12);
+        assert_eq!(Symmetry::Diagonal.groups(4, 4).unwrap().len(), 10);
+        assert!(Symmetry::Diagonal.groups(6, 4).is_err());
+    }
+}
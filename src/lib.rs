@@ -1,4 +1,5 @@
 pub mod analyzer;
+pub mod game;
 pub mod generator;
 pub mod puzzle;
 pub mod solver;
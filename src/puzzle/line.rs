@@ -0,0 +1,290 @@
+use crate::puzzle::{self, marker, rules};
+
+/// A horizontal or vertical line of the puzzle, or one of the main diagonals
+/// of a square puzzle of the given size. A line is a view on the cells of the
+/// puzzle, where the i-th cell of the line is at the position given by the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    Row(usize),
+    Column(usize),
+    Diagonal(usize),     // from the top left to the bottom right
+    AntiDiagonal(usize), // from the top right to the bottom left
+}
+
+impl Line {
+    /// Return the number of cells on this line.
+    pub fn length(&self, puzzle: &puzzle::Puzzle) -> usize {
+        match *self {
+            Line::Row(_) => puzzle.width(),
+            Line::Column(_) => puzzle.height(),
+            Line::Diagonal(size) | Line::AntiDiagonal(size) => size,
+        }
+    }
+
+    /// Return the coordinates (x, y) of the i-th cell on this line.
+    pub fn position(&self, i: usize) -> (usize, usize) {
+        match *self {
+            Line::Row(y) => (i, y),
+            Line::Column(x) => (x, i),
+            Line::Diagonal(_) => (i, i),
+            Line::AntiDiagonal(size) => (size - 1 - i, i),
+        }
+    }
+
+    /// Return the coordinates (x, y) of all the cells on this line.
+    pub fn positions(&self, puzzle: &puzzle::Puzzle) -> Vec<(usize, usize)> {
+        (0..self.length(puzzle)).map(|i| self.position(i)).collect()
+    }
+
+    /// Return the index on this line of the cell at (x, y), if it is on it.
+    pub fn index(&self, x: usize, y: usize) -> Option<usize> {
+        match *self {
+            Line::Row(row) => (y == row).then_some(x),
+            Line::Column(column) => (x == column).then_some(y),
+            Line::Diagonal(_) => (x == y).then_some(y),
+            Line::AntiDiagonal(size) => (x + y + 1 == size).then_some(y),
+        }
+    }
+
+    /// Return the values of all the cells on this line.
+    pub fn values(&self, puzzle: &puzzle::Puzzle) -> Vec<Option<u8>> {
+        self.positions(puzzle)
+            .into_iter()
+            .map(|(x, y)| puzzle[y][x])
+            .collect()
+    }
+
+    /// Give the i-th cell on this line the given value, or empty it if none is
+    /// given.
+    pub fn set(&self, puzzle: &mut puzzle::Puzzle, i: usize, value: Option<u8>) {
+        let (x, y) = self.position(i);
+        puzzle[y][x] = value;
+    }
+
+    /// Return the markers between the cells on this line, the i-th marker is
+    /// between the i-th and the next cell. The cells on a diagonal are no
+    /// neighbours, so there are no markers between them.
+    pub fn markers(&self, puzzle: &puzzle::Puzzle) -> Vec<Option<marker::Marker>> {
+        match *self {
+            Line::Row(y) => (0..puzzle.width() - 1)
+                .map(|x| puzzle.marker(x, y, marker::Direction::Right))
+                .collect(),
+            Line::Column(x) => (0..puzzle.height() - 1)
+                .map(|y| puzzle.marker(x, y, marker::Direction::Down))
+                .collect(),
+            Line::Diagonal(size) | Line::AntiDiagonal(size) => vec![None; size - 1],
+        }
+    }
+
+    /// Return the number of ones this line has, if given by a clue.
+    pub fn clue(&self, puzzle: &puzzle::Puzzle) -> Option<usize> {
+        match *self {
+            Line::Row(y) => puzzle.row_clue(y),
+            Line::Column(x) => puzzle.column_clue(x),
+            Line::Diagonal(_) | Line::AntiDiagonal(_) => None,
+        }
+    }
+
+    /// Give this line the given clue, or remove its clue if none is given. Only
+    /// the rows and columns can have a clue.
+    pub fn set_clue(&self, puzzle: &mut puzzle::Puzzle, clue: Option<usize>) {
+        match *self {
+            Line::Row(y) => puzzle.set_row_clue(y, clue),
+            Line::Column(x) => puzzle.set_column_clue(x, clue),
+            Line::Diagonal(_) | Line::AntiDiagonal(_) => (),
+        }
+    }
+
+    /// Return the rules which this line follows.
+    pub fn rules(&self, puzzle: &puzzle::Puzzle) -> rules::Rules {
+        match *self {
+            Line::Row(_) | Line::Column(_) => *puzzle.rules(),
+            Line::Diagonal(_) | Line::AntiDiagonal(_) => puzzle.rules().on_diagonal(),
+        }
+    }
+
+    /// Return for all the cells on this line whether they are blocked.
+    pub fn blocked(&self, puzzle: &puzzle::Puzzle) -> Vec<bool> {
+        self.positions(puzzle)
+            .into_iter()
+            .map(|(x, y)| puzzle.blocked(x, y))
+            .collect()
+    }
+
+    /// Return whether all the cells on this line which are not blocked have a
+    /// value.
+    pub fn full(&self, puzzle: &puzzle::Puzzle) -> bool {
+        let blocked = self.blocked(puzzle);
+        return self
+            .values(puzzle)
+            .iter()
+            .zip(blocked)
+            .all(|(value, blocked)| value.is_some() || blocked);
+    }
+
+    /// Return all the lines in the same direction as this one, including this
+    /// line itself. A diagonal has no other lines in its direction.
+    pub fn parallel(&self, puzzle: &puzzle::Puzzle) -> Vec<Line> {
+        match *self {
+            Line::Row(_) => (0..puzzle.height()).map(Line::Row).collect(),
+            Line::Column(_) => (0..puzzle.width()).map(Line::Column).collect(),
+            Line::Diagonal(_) | Line::AntiDiagonal(_) => vec![*self],
+        }
+    }
+
+    /// Return the segments of this line between its blocked cells.
+    pub fn segments(&self, puzzle: &puzzle::Puzzle) -> Vec<Segment> {
+        let blocked = self.blocked(puzzle);
+        let rules = self.rules(puzzle);
+        return rules
+            .segments(&blocked)
+            .into_iter()
+            .map(|cells| Segment {
+                line: *self,
+                rules: rules.segment(&cells, &blocked),
+                cells,
+            })
+            .collect();
+    }
+}
+
+/// The cells of a line between its blocked cells, which follow the rules on
+/// their own. Without blocked cells the whole line is one segment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub line: Line,
+    pub cells: Vec<usize>, // the indices of the cells on the line
+    pub rules: rules::Rules,
+}
+
+impl Segment {
+    /// Return the values of all the cells in this segment.
+    pub fn values(&self, puzzle: &puzzle::Puzzle) -> Vec<Option<u8>> {
+        let values = self.line.values(puzzle);
+        return self.cells.iter().map(|&i| values[i]).collect();
+    }
+
+    /// Return the markers between the cells in this segment, the i-th marker is
+    /// between the i-th and the next cell.
+    pub fn markers(&self, puzzle: &puzzle::Puzzle) -> Vec<Option<marker::Marker>> {
+        between(&self.cells, &self.line.markers(puzzle))
+    }
+
+    /// Return the coordinates (x, y) of the i-th cell in this segment.
+    pub fn position(&self, i: usize) -> (usize, usize) {
+        self.line.position(self.cells[i])
+    }
+}
+
+/// Return all the rows, then all the columns and then the main diagonals if
+/// the rules apply to them.
+pub fn all(puzzle: &puzzle::Puzzle) -> Vec<Line> {
+    (0..puzzle.height())
+        .map(Line::Row)
+        .chain((0..puzzle.width()).map(Line::Column))
+        .chain(diagonals(puzzle))
+        .collect()
+}
+
+/// Return the main diagonals of the puzzle if the rules apply to them.
+pub fn diagonals(puzzle: &puzzle::Puzzle) -> Vec<Line> {
+    match puzzle.rules().diagonals.any() {
+        true => vec![
+            Line::Diagonal(puzzle.width()),
+            Line::AntiDiagonal(puzzle.width()),
+        ],
+        false => Vec::new(),
+    }
+}
+
+/// Return the lines which go through the cell at (x, y), that is its row and
+/// column and the diagonals it is on if the rules apply to them.
+pub fn through(puzzle: &puzzle::Puzzle, x: usize, y: usize) -> Vec<Line> {
+    let mut lines = vec![Line::Row(y), Line::Column(x)];
+    lines.extend(
+        diagonals(puzzle)
+            .into_iter()
+            .filter(|diagonal| diagonal.index(x, y).is_some()),
+    );
+    return lines;
+}
+
+/// Return the markers between the given cells of a line, where there is no
+/// marker between cells which are not neighbours.
+pub fn between(cells: &[usize], markers: &[Option<marker::Marker>]) -> Vec<Option<marker::Marker>> {
+    cells
+        .windows(2)
+        .map(|pair| match pair[0] + 1 == pair[1] {
+            true => markers.get(pair[0]).copied().flatten(),
+            false => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions() {
+        let puzzle = puzzle::Puzzle::from_codex("1a1j0a0", 4, 4).unwrap();
+        assert_eq!(
+            Line::Row(0).values(&puzzle),
+            vec![Some(1), None, Some(1), None]
+        );
+        assert_eq!(
+            Line::Column(1).values(&puzzle),
+            vec![None, None, None, Some(0)]
+        );
+        assert_eq!(Line::Row(3).position(1), (1, 3));
+        assert_eq!(Line::Column(3).position(1), (3, 1));
+        assert_eq!(Line::AntiDiagonal(4).position(1), (2, 1));
+
+        // the index is the inverse of the position
+        for line in [Line::Row(2), Line::Column(1), Line::AntiDiagonal(4)] {
+            for i in 0..4 {
+                let (x, y) = line.position(i);
+                assert_eq!(line.index(x, y), Some(i));
+            }
+        }
+        assert_eq!(Line::Row(2).index(1, 1), None);
+        assert_eq!(Line::Diagonal(4).index(1, 2), None);
+    }
+
+    #[test]
+    fn mutation() {
+        let mut puzzle = puzzle::Puzzle::new(4, 4).unwrap();
+        Line::Column(2).set(&mut puzzle, 1, Some(1));
+        assert_eq!(puzzle[1][2], Some(1));
+        Line::AntiDiagonal(4).set(&mut puzzle, 0, Some(0));
+        assert_eq!(puzzle[0][3], Some(0));
+        Line::Row(3).set_clue(&mut puzzle, Some(2));
+        assert_eq!(puzzle.row_clue(3), Some(2));
+        assert_eq!(Line::Row(3).clue(&puzzle), Some(2));
+    }
+
+    #[test]
+    fn lines() {
+        // the diagonals only take part when the rules apply to them
+        let puzzle = puzzle::Puzzle::from_codex("1a1j0a0", 4, 4).unwrap();
+        assert_eq!(
+            Line::Diagonal(4).values(&puzzle),
+            vec![Some(1), None, None, Some(0)]
+        );
+        assert_eq!(Line::Diagonal(4).rules(&puzzle).run, None);
+        assert!(diagonals(&puzzle).is_empty());
+        assert_eq!(all(&puzzle).len(), 8);
+        assert_eq!(Line::Column(1).parallel(&puzzle).len(), 4);
+
+        let rules = rules::Rules::diagonal();
+        let puzzle = puzzle::Puzzle::from_codex_with("1a1j0a0", 4, 4, rules).unwrap();
+        assert_eq!(
+            diagonals(&puzzle),
+            vec![Line::Diagonal(4), Line::AntiDiagonal(4)]
+        );
+        assert_eq!(all(&puzzle).len(), 10);
+        assert_eq!(through(&puzzle, 1, 1).len(), 3);
+        assert_eq!(through(&puzzle, 1, 2).len(), 3);
+        assert_eq!(through(&puzzle, 1, 0).len(), 2);
+    }
+}
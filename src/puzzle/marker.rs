@@ -0,0 +1,63 @@
+/// A marker between two neighbouring cells, which says whether the cells have
+/// the same value or not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Marker {
+    Equal,    // written as = between the cells
+    Opposite, // written as × between the cells
+}
+
+/// The side of a cell at which its neighbour is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Right,
+    Down,
+}
+
+impl Marker {
+    /// Return the marker which holds between the given values.
+    pub fn between(a: u8, b: u8) -> Marker {
+        match a == b {
+            true => Marker::Equal,
+            false => Marker::Opposite,
+        }
+    }
+
+    /// Return whether the marker holds between the given values.
+    pub fn allows(&self, a: u8, b: u8) -> bool {
+        *self == Marker::between(a, b)
+    }
+
+    /// Return the value of the neighbour of a cell with the given value, when
+    /// there are only the two symbols 0 and 1.
+    pub fn apply(&self, v: u8) -> u8 {
+        match self {
+            Marker::Equal => v,
+            Marker::Opposite => v ^ 1,
+        }
+    }
+}
+
+impl Direction {
+    /// Return the position of the neighbour of the cell at (x, y).
+    pub fn neighbour(&self, x: usize, y: usize) -> (usize, usize) {
+        match self {
+            Direction::Right => (x + 1, y),
+            Direction::Down => (x, y + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        assert_eq!(Marker::between(1, 1), Marker::Equal);
+        assert_eq!(Marker::between(0, 1), Marker::Opposite);
+        assert_eq!(Marker::Equal.apply(0), 0);
+        assert_eq!(Marker::Opposite.apply(0), 1);
+        assert!(Marker::Opposite.allows(0, 2));
+        assert!(!Marker::Equal.allows(0, 2));
+    }
+}
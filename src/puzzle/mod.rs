@@ -1,13 +1,44 @@
+use itertools::Itertools;
 use std::{char, fmt, result};
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Puzzle(Vec<Vec<Option<u8>>>);
+pub mod line;
+pub mod marker;
+pub mod rules;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Puzzle {
+    cells: Vec<Vec<Option<u8>>>,
+    rules: rules::Rules,
+    right: Vec<Vec<Option<marker::Marker>>>, // the markers between horizontal neighbours
+    down: Vec<Vec<Option<marker::Marker>>>,  // the markers between vertical neighbours
+    blocked: Vec<Vec<bool>>,                 // the cells which are not part of the grid
+    rows: Vec<Option<usize>>,                // the number of ones in every row, if given
+    columns: Vec<Option<usize>>,             // the number of ones in every column, if given
+}
 
 impl Puzzle {
     /// Create a new empty puzzle with the given sizes.
     pub fn new(width: usize, height: usize) -> result::Result<Puzzle, String> {
-        Self::check(width, height)?;
-        return Ok(Puzzle(vec![vec![None; width]; height]));
+        Self::new_with(width, height, rules::Rules::default())
+    }
+
+    /// Create a new empty puzzle with the given sizes which follows the given
+    /// rules.
+    pub fn new_with(
+        width: usize,
+        height: usize,
+        rules: rules::Rules,
+    ) -> result::Result<Puzzle, String> {
+        rules.check(width, height)?;
+        return Ok(Puzzle {
+            cells: vec![vec![None; width]; height],
+            rules,
+            right: vec![vec![None; width - 1]; height],
+            down: vec![vec![None; width]; height - 1],
+            blocked: vec![vec![false; width]; height],
+            rows: vec![None; height],
+            columns: vec![None; width],
+        });
     }
 
     pub fn width(&self) -> usize {
@@ -15,77 +46,414 @@ impl Puzzle {
     }
 
     pub fn height(&self) -> usize {
-        self.0.len()
+        self.cells.len()
+    }
+
+    pub fn rules(&self) -> &rules::Rules {
+        &self.rules
+    }
+
+    /// Return the number of cells which have a value.
+    pub fn givens(&self) -> usize {
+        self.cells.iter().flatten().filter(|v| v.is_some()).count()
+    }
+
+    /// Return the number of cells which are not blocked, which is the number of
+    /// values a solution has.
+    pub fn size(&self) -> usize {
+        self.blocked.iter().flatten().filter(|&&b| !b).count()
+    }
+
+    /// Return whether the cell at (x, y) is blocked, a blocked cell does not have
+    /// a value and splits its lines into segments.
+    pub fn blocked(&self, x: usize, y: usize) -> bool {
+        self.blocked[y][x]
     }
 
-    /// Check whether the width and height big enough and a multiple of two.
-    fn check(width: usize, height: usize) -> result::Result<(), String> {
-        if width % 2 != 0 || height % 2 != 0 {
-            return Err("Width and height have to be a multiple of two.".to_string());
+    /// Block or unblock the cell at (x, y), which removes its value.
+    pub fn set_blocked(&mut self, x: usize, y: usize, blocked: bool) {
+        self.blocked[y][x] = blocked;
+        self.cells[y][x] = None;
+    }
+
+    /// Return the marker between the cell at (x, y) and its neighbour in the
+    /// given direction.
+    pub fn marker(
+        &self,
+        x: usize,
+        y: usize,
+        direction: marker::Direction,
+    ) -> Option<marker::Marker> {
+        match direction {
+            marker::Direction::Right => self.right[y][x],
+            marker::Direction::Down => self.down[y][x],
         }
-        if width < 4 || height < 4 {
-            return Err("Width and height have to be at least four.".to_string());
+    }
+
+    /// Put a marker between the cell at (x, y) and its neighbour in the given
+    /// direction, or remove the marker if none is given.
+    pub fn set_marker(
+        &mut self,
+        x: usize,
+        y: usize,
+        direction: marker::Direction,
+        marker: Option<marker::Marker>,
+    ) {
+        match direction {
+            marker::Direction::Right => self.right[y][x] = marker,
+            marker::Direction::Down => self.down[y][x] = marker,
         }
-        return Ok(());
     }
 
-    /// Return the codex representation of this puzzle.
-    pub fn codex(&self) -> String {
-        let mut codex = String::new();
-        let mut count = 0;
+    /// Return the number of markers between cells.
+    pub fn markers(&self) -> usize {
+        self.right
+            .iter()
+            .chain(self.down.iter())
+            .flatten()
+            .filter(|m| m.is_some())
+            .count()
+    }
 
-        for y in 0..self.height() {
-            for x in 0..self.width() {
-                if self[y][x].is_none() {
-                    count += 1;
-                    if count == 26 {
-                        codex.push('z');
-                        count -= 26;
-                    }
-                } else {
-                    if count > 0 {
-                        codex.push(char::from_u32('a' as u32 + count - 1).unwrap());
-                        count = 0;
-                    }
-                    codex.push(char::from_digit(self[y][x].unwrap().into(), 10).unwrap());
-                }
-            }
+    /// Return the number of ones the row at the given height has, if given.
+    pub fn row_clue(&self, y: usize) -> Option<usize> {
+        self.rows[y]
+    }
+
+    /// Return the number of ones the column at the given width has, if given.
+    pub fn column_clue(&self, x: usize) -> Option<usize> {
+        self.columns[x]
+    }
+
+    /// Give the number of ones of the row at the given height, or remove the
+    /// clue if none is given.
+    pub fn set_row_clue(&mut self, y: usize, clue: Option<usize>) {
+        self.rows[y] = clue;
+    }
+
+    /// Give the number of ones of the column at the given width, or remove the
+    /// clue if none is given.
+    pub fn set_column_clue(&mut self, x: usize, clue: Option<usize>) {
+        self.columns[x] = clue;
+    }
+
+    /// Return the number of rows and columns which have a clue.
+    pub fn clues(&self) -> usize {
+        self.rows
+            .iter()
+            .chain(self.columns.iter())
+            .filter(|c| c.is_some())
+            .count()
+    }
+
+    /// Return all the places where a marker can be, first those between the
+    /// horizontal neighbours row by row and then those between the vertical
+    /// neighbours.
+    pub fn edges(&self) -> Vec<(usize, usize, marker::Direction)> {
+        let (width, height) = (self.width(), self.height());
+        let right =
+            (0..height).flat_map(|y| (0..width - 1).map(move |x| (x, y, marker::Direction::Right)));
+        let down =
+            (0..height - 1).flat_map(|y| (0..width).map(move |x| (x, y, marker::Direction::Down)));
+        return right.chain(down).collect();
+    }
+
+    /// Return the codex representation of this puzzle, where a value is written
+    /// as its digit and a blocked cell as an underscore. When there are markers
+    /// they follow the cells after a colon, in the order of the edges, where a 0
+    /// is an equal and a 1 an opposite marker. When there are clues they follow
+    /// the markers after another colon, as the numbers of the rows and then of
+    /// the columns separated by commas, which are left out for the lines without
+    /// a clue.
+    pub fn codex(&self) -> String {
+        let cells = self
+            .cells
+            .iter()
+            .flatten()
+            .zip(self.blocked.iter().flatten());
+        let mut codex = encode(cells.map(|(value, &blocked)| match blocked {
+            true => Some('_'),
+            false => value.map(digit),
+        }));
+        if self.markers() > 0 {
+            let markers = self.edges().into_iter().map(|(x, y, direction)| {
+                self.marker(x, y, direction)
+                    .map(|marker| digit(marker.apply(0)))
+            });
+            codex.push(':');
+            codex.push_str(&encode(markers));
+        } else if self.clues() > 0 {
+            codex.push(':');
         }
-        if count > 0 {
-            codex.push(char::from_u32('a' as u32 + count - 1).unwrap());
+        if self.clues() > 0 {
+            let clues: Vec<_> = self
+                .rows
+                .iter()
+                .chain(self.columns.iter())
+                .map(|clue| clue.map_or(String::new(), |c| c.to_string()))
+                .collect();
+            codex.push(':');
+            codex.push_str(&clues.join(","));
         }
         return codex;
     }
 
     /// Create a new puzzle from a codex string.
     pub fn from_codex(codex: &str, width: usize, height: usize) -> result::Result<Puzzle, String> {
-        Self::check(width, height)?;
-
-        let mut puzzle = Puzzle(vec![vec![None; width]; height]);
-        let mut count: usize = 0;
-
-        for mut c in codex.chars() {
-            if c == '0' || c == '1' {
-                puzzle[count / width][count % width] =
-                    Some(c.to_digit(10).unwrap().try_into().unwrap());
-                count += 1;
-            } else {
-                if !c.is_ascii_lowercase() {
-                    return Err("Make sure all characters are ascii lowercase.".to_string());
+        Self::from_codex_with(codex, width, height, rules::Rules::default())
+    }
+
+    /// Create a new puzzle which follows the given rules from a codex string.
+    pub fn from_codex_with(
+        codex: &str,
+        width: usize,
+        height: usize,
+        rules: rules::Rules,
+    ) -> result::Result<Puzzle, String> {
+        let mut puzzle = Self::new_with(width, height, rules)?;
+        let symbols: String = (0..rules.symbols).map(digit).chain(['_']).collect();
+        let mut sections = codex.split(':');
+        let (cells, markers, clues) = (sections.next().unwrap(), sections.next(), sections.next());
+        if sections.next().is_some() {
+            return Err("The codex has too many sections.".to_string());
+        }
+
+        for (i, symbol) in decode(cells, width * height, &symbols)?
+            .into_iter()
+            .enumerate()
+        {
+            match symbol {
+                Some('_') => puzzle.set_blocked(i % width, i / width, true),
+                _ => puzzle[i / width][i % width] = symbol.map(value),
+            }
+        }
+
+        if let Some(markers) = markers.filter(|markers| !markers.is_empty()) {
+            let edges = puzzle.edges();
+            for ((x, y, direction), symbol) in edges.iter().zip(decode(markers, edges.len(), "01")?)
+            {
+                let marker = symbol.map(|c| marker::Marker::between(0, value(c)));
+                puzzle.set_marker(*x, *y, *direction, marker);
+            }
+        }
+
+        if let Some(clues) = clues {
+            if rules.symbols != 2 {
+                return Err("Only puzzles with two symbols can have clues.".to_string());
+            }
+            let clues = clues
+                .split(',')
+                .map(|clue| match clue {
+                    "" => Ok(None),
+                    _ => clue.parse().map(Some),
+                })
+                .collect::<result::Result<Vec<_>, _>>()
+                .map_err(|_| "Make sure the clues are numbers.".to_string())?;
+            if clues.len() != height + width {
+                return Err("The number of clues is invalid.".to_string());
+            }
+            puzzle.rows = clues[..height].to_vec();
+            puzzle.columns = clues[height..].to_vec();
+        }
+
+        return Ok(puzzle);
+    }
+
+    /// Return the puzzle rotated clockwise by 90 degrees.
+    pub fn rotate(&self) -> Puzzle {
+        self.image(true, true, false, &self.order())
+    }
+
+    /// Return the puzzle mirrored in its main diagonal, so the rows become the
+    /// columns and the other way around.
+    pub fn transpose(&self) -> Puzzle {
+        self.image(true, false, false, &self.order())
+    }
+
+    /// Return the puzzle mirrored from left to right.
+    pub fn flip_horizontal(&self) -> Puzzle {
+        self.image(false, true, false, &self.order())
+    }
+
+    /// Return the puzzle mirrored from top to bottom.
+    pub fn flip_vertical(&self) -> Puzzle {
+        self.image(false, false, true, &self.order())
+    }
+
+    /// Return the puzzle with the order of the symbols reversed, so with two
+    /// symbols every zero is replaced by a one and the other way around.
+    pub fn swap(&self) -> Puzzle {
+        let reversed: Vec<_> = self.order().into_iter().rev().collect();
+        self.image(false, false, false, &reversed)
+    }
+
+    /// Return the canonical representative of all the puzzles which are the
+    /// same game as this one, that is all rotations and mirror images, with
+    /// every permutation of the symbols. This is the smallest of those puzzles,
+    /// comparing row by row where an empty cell comes before any value.
+    pub fn canonical(&self) -> Puzzle {
+        let mut images = Vec::new();
+        for transpose in [false, true] {
+            if transpose && self.width() != self.height() {
+                continue;
+            }
+            for flip_x in [false, true] {
+                for flip_y in [false, true] {
+                    let image = self.image(transpose, flip_x, flip_y, &self.order());
+                    for symbols in image.labels() {
+                        images.push(self.image(transpose, flip_x, flip_y, &symbols));
+                    }
                 }
-                while c >= 'a' {
-                    puzzle[count / width][count % width] = None;
-                    count += 1;
-                    c = (c as u32 - 1).try_into().unwrap();
+            }
+        }
+        return images.into_iter().min().unwrap();
+    }
+
+    /// Return a hash of the canonical form, which is the same for puzzles which
+    /// are the same game. The hash does not depend on the platform or version.
+    pub fn canonical_hash(&self) -> u64 {
+        let canonical = self.canonical();
+
+        // hash the rules, sizes and cells with 64-bit FNV-1a
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let header = [
+            canonical.rules.code(),
+            canonical.width() as u64,
+            canonical.height() as u64,
+        ];
+        let blocked = canonical.blocked.iter().flatten();
+        let symbols = canonical.rules.symbols as u64;
+        let cells = canonical
+            .cells
+            .iter()
+            .flatten()
+            .zip(blocked)
+            .map(|v| match v {
+                (_, true) => symbols + 1,
+                (None, false) => symbols,
+                (Some(v), false) => *v as u64,
+            });
+        let mut values: Vec<u64> = header.into_iter().chain(cells).collect();
+
+        // the markers and clues are only hashed when there are any, so the hashes
+        // of the puzzles without them stay the same
+        if canonical.clues() > 0 {
+            let clues = canonical.rows.iter().chain(canonical.columns.iter());
+            values.extend(clues.map(|clue| clue.map_or(0, |c| c as u64 + 1)));
+        }
+        if canonical.markers() > 0 {
+            values.extend(canonical.edges().into_iter().map(|(x, y, direction)| {
+                match canonical.marker(x, y, direction) {
+                    None => 2,
+                    Some(marker) => marker.apply(0) as u64,
                 }
+            }));
+        }
+        for value in values {
+            hash ^= value;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        return hash;
+    }
+
+    /// Return the permutations of the symbols which can make this puzzle the
+    /// smallest, where the symbols are numbered in the order in which they first
+    /// appear. The symbols which do not appear only change the clues, so their
+    /// order only matters when there are clues.
+    fn labels(&self) -> Vec<Vec<u8>> {
+        let mut seen = Vec::new();
+        for &v in self.cells.iter().flatten().flatten() {
+            if !seen.contains(&v) {
+                seen.push(v);
             }
         }
+        let unseen: Vec<_> = self
+            .order()
+            .into_iter()
+            .filter(|v| !seen.contains(v))
+            .collect();
+        let orders: Vec<_> = match self.clues() > 0 {
+            true => unseen.iter().copied().permutations(unseen.len()).collect(),
+            false => vec![unseen],
+        };
+
+        return orders
+            .into_iter()
+            .map(|rest| {
+                let mut symbols = vec![0; self.rules.symbols as usize];
+                for (label, &v) in seen.iter().chain(rest.iter()).enumerate() {
+                    symbols[v as usize] = label as u8;
+                }
+                symbols
+            })
+            .collect();
+    }
+
+    /// Return the symbols in their own order, which leaves the values of an image
+    /// unchanged.
+    fn order(&self) -> Vec<u8> {
+        (0..self.rules.symbols).collect()
+    }
+
+    /// Return the puzzle which is first transposed, then mirrored left to right
+    /// and top to bottom, if asked for, and finally has every value v replaced by
+    /// the v-th of the given symbols.
+    fn image(&self, transpose: bool, flip_x: bool, flip_y: bool, symbols: &[u8]) -> Puzzle {
+        let (width, height) = match transpose {
+            true => (self.height(), self.width()),
+            false => (self.width(), self.height()),
+        };
 
-        if count != width * height {
-            return Err("The size of the codex is invalid.".to_string());
+        let position = |x: usize, y: usize| {
+            let (ix, iy) = if transpose { (y, x) } else { (x, y) };
+            let ix = if flip_x { width - 1 - ix } else { ix };
+            let iy = if flip_y { height - 1 - iy } else { iy };
+            (ix, iy)
+        };
+
+        let mut image = Puzzle::new_with(width, height, self.rules).unwrap();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let (ix, iy) = position(x, y);
+                image[iy][ix] = self[y][x].map(|v| symbols[v as usize]);
+                image.blocked[iy][ix] = self.blocked[y][x];
+            }
         }
 
-        return Ok(puzzle);
+        // replacing the symbols does not change whether neighbours are equal
+        for (x, y, direction) in self.edges() {
+            let (nx, ny) = direction.neighbour(x, y);
+            let (a, b) = (position(x, y), position(nx, ny));
+            let to = match a.1 == b.1 {
+                true => marker::Direction::Right,
+                false => marker::Direction::Down,
+            };
+            image.set_marker(a.0.min(b.0), a.1.min(b.1), to, self.marker(x, y, direction));
+        }
+
+        // swapping the symbols turns the number of ones into the number of zeros,
+        // only puzzles with two symbols have clues
+        let clue = |clue: Option<usize>, open: usize| match symbols[1] {
+            1 => clue,
+            _ => clue.map(|c| open.saturating_sub(c)),
+        };
+        for y in 0..self.height() {
+            let open = (0..self.width()).filter(|&x| !self.blocked[y][x]).count();
+            let (ix, iy) = position(0, y);
+            match transpose {
+                true => image.columns[ix] = clue(self.rows[y], open),
+                false => image.rows[iy] = clue(self.rows[y], open),
+            }
+        }
+        for x in 0..self.width() {
+            let open = (0..self.height()).filter(|&y| !self.blocked[y][x]).count();
+            let (ix, iy) = position(x, 0);
+            match transpose {
+                true => image.rows[iy] = clue(self.columns[x], open),
+                false => image.columns[ix] = clue(self.columns[x], open),
+            }
+        }
+        return image;
     }
 }
 
@@ -93,39 +461,135 @@ impl std::ops::Index<usize> for Puzzle {
     type Output = Vec<Option<u8>>;
 
     fn index(&self, index: usize) -> &Self::Output {
-        return &self.0[index];
+        return &self.cells[index];
     }
 }
 
 impl std::ops::IndexMut<usize> for Puzzle {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        return &mut self.0[index];
+        return &mut self.cells[index];
     }
 }
 
 impl fmt::Display for Puzzle {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = |marker: Option<marker::Marker>| match marker {
+            Some(marker::Marker::Equal) => "=",
+            Some(marker::Marker::Opposite) => "x",
+            None => " ",
+        };
+
         for y in 0..self.height() {
             for x in 0..self.width() {
                 write!(
                     f,
-                    "{} ",
-                    match self[y][x] {
-                        Some(0) => "0",
-                        Some(1) => "1",
-                        _ => ".",
+                    "{}{}",
+                    match (self[y][x], self.blocked[y][x]) {
+                        (_, true) => '#',
+                        (Some(v), _) => digit(v),
+                        _ => '.',
+                    },
+                    match x + 1 < self.width() {
+                        true => symbol(self.marker(x, y, marker::Direction::Right)),
+                        false => " ",
                     }
                 )?;
             }
+            if self.clues() > 0 {
+                write!(
+                    f,
+                    "| {}",
+                    self.rows[y].map_or(String::new(), |c| c.to_string())
+                )?;
+            }
             write!(f, "\n")?;
+
+            // the markers between the rows get a line of their own
+            if y + 1 < self.height() && self.markers() > 0 {
+                for x in 0..self.width() {
+                    write!(f, "{} ", symbol(self.marker(x, y, marker::Direction::Down)))?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        // the clues of the columns are written below them
+        if self.clues() > 0 {
+            for clue in &self.columns {
+                write!(f, "{:<2}", clue.map_or(String::new(), |c| c.to_string()))?;
+            }
+            writeln!(f)?;
         }
         Ok(())
     }
 }
 
+/// Return the digit which is written for the given value.
+fn digit(v: u8) -> char {
+    char::from_digit(v.into(), 10).unwrap()
+}
+
+/// Return the value of the given digit.
+fn value(c: char) -> u8 {
+    c.to_digit(10).unwrap().try_into().unwrap()
+}
+
+/// Encode the symbols, where every symbol is written as it is and a run of up
+/// to 26 missing symbols as a lowercase letter.
+fn encode(values: impl Iterator<Item = Option<char>>) -> String {
+    let mut codex = String::new();
+    let mut count = 0;
+
+    for value in values {
+        if value.is_none() {
+            count += 1;
+            if count == 26 {
+                codex.push('z');
+                count -= 26;
+            }
+        } else {
+            if count > 0 {
+                codex.push(char::from_u32('a' as u32 + count - 1).unwrap());
+                count = 0;
+            }
+            codex.push(value.unwrap());
+        }
+    }
+    if count > 0 {
+        codex.push(char::from_u32('a' as u32 + count - 1).unwrap());
+    }
+    return codex;
+}
+
+/// Decode the given number of symbols from the codex, which are only allowed
+/// to be one of the given symbols.
+fn decode(codex: &str, length: usize, symbols: &str) -> result::Result<Vec<Option<char>>, String> {
+    let mut values = Vec::with_capacity(length);
+
+    for mut c in codex.chars() {
+        if symbols.contains(c) {
+            values.push(Some(c));
+        } else {
+            if !c.is_ascii_lowercase() {
+                return Err("Make sure all characters are ascii lowercase.".to_string());
+            }
+            while c >= 'a' {
+                values.push(None);
+                c = (c as u32 - 1).try_into().unwrap();
+            }
+        }
+    }
+
+    if values.len() != length {
+        return Err("The size of the codex is invalid.".to_string());
+    }
+    return Ok(values);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn from_codex_error() {
@@ -170,6 +634,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn codex_large() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let width = 2 * rng.gen_range(2..=15);
+            let height = 2 * rng.gen_range(2..=15);
+            let empty = rng.gen_range(0.0..=1.0);
+
+            // long runs of empty cells need more than one character
+            let mut puzzle = Puzzle::new(width, height).unwrap();
+            for y in 0..height {
+                for x in 0..width {
+                    if !rng.gen_bool(empty) {
+                        puzzle[y][x] = Some(rng.gen_range(0..=1));
+                    }
+                }
+            }
+
+            let codex = puzzle.codex();
+            assert_eq!(Puzzle::from_codex(&codex, width, height), Ok(puzzle));
+        }
+    }
+
     #[test]
     fn size() {
         let puzzle = Puzzle::from_codex("a1d11d1d0f", 6, 4).unwrap();
@@ -177,6 +664,252 @@ mod tests {
         assert!(puzzle.height() == 4);
     }
 
+    #[test]
+    fn relaxed() {
+        assert!(Puzzle::from_codex("a1b0t", 5, 5).is_err());
+        let puzzle = Puzzle::from_codex_with("a1b0t", 5, 5, rules::Rules::relaxed()).unwrap();
+        assert_eq!(puzzle.codex(), "a1b0t");
+        assert_eq!(puzzle.rules(), &rules::Rules::relaxed());
+        assert_eq!(puzzle.rotate().rules(), &rules::Rules::relaxed());
+        assert_eq!(puzzle.canonical().rules(), &rules::Rules::relaxed());
+
+        // the same values under different rules are a different puzzle
+        let exact = Puzzle::from_codex("a1d11d1d0f", 6, 4).unwrap();
+        let relaxed = Puzzle::from_codex_with("a1d11d1d0f", 6, 4, rules::Rules::relaxed()).unwrap();
+        assert_ne!(exact.canonical_hash(), relaxed.canonical_hash());
+    }
+
+    #[test]
+    fn markers() {
+        let puzzle = Puzzle::from_codex("1e1h1:0b0g1l", 4, 4).unwrap();
+        assert_eq!(puzzle.markers(), 3);
+        assert_eq!(
+            puzzle.marker(0, 0, marker::Direction::Right),
+            Some(marker::Marker::Equal)
+        );
+        assert_eq!(
+            puzzle.marker(2, 3, marker::Direction::Right),
+            Some(marker::Marker::Opposite)
+        );
+        assert_eq!(puzzle.marker(0, 0, marker::Direction::Down), None);
+        assert_eq!(puzzle.codex(), "1e1h1:0b0g1l");
+        assert!(Puzzle::from_codex("1e1h1:0b0g1", 4, 4).is_err());
+        assert_eq!(
+            Puzzle::from_codex("1e1h1:x", 4, 4).unwrap().codex(),
+            "1e1h1"
+        );
+
+        // the markers move along with the cells
+        let rotated = puzzle.rotate();
+        assert_eq!(
+            rotated.marker(3, 0, marker::Direction::Down),
+            Some(marker::Marker::Equal)
+        );
+        assert_eq!(
+            rotated.marker(0, 2, marker::Direction::Down),
+            Some(marker::Marker::Opposite)
+        );
+        assert_eq!(rotated.rotate().rotate().rotate(), puzzle);
+        assert_eq!(puzzle.transpose().transpose(), puzzle);
+        assert_eq!(puzzle.swap().markers(), 3);
+        assert_eq!(puzzle.canonical(), rotated.flip_vertical().canonical());
+
+        let plain = Puzzle::from_codex("1e1h1", 4, 4).unwrap();
+        assert_ne!(puzzle.canonical_hash(), plain.canonical_hash());
+        assert_eq!(
+            format!("{}", plain),
+            format!("{}", Puzzle::from_codex("1e1h1:x", 4, 4).unwrap())
+        );
+        assert!(format!("{}", puzzle).starts_with("1=. . . \n        \n"));
+    }
+
+    #[test]
+    fn blocked() {
+        let mut puzzle = Puzzle::from_codex("__a1k_", 4, 4).unwrap();
+        assert!(puzzle.blocked(0, 0) && puzzle.blocked(1, 0) && puzzle.blocked(3, 3));
+        assert!(!puzzle.blocked(2, 0));
+        assert_eq!(puzzle.size(), 13);
+        assert_eq!(puzzle.givens(), 1);
+        assert_eq!(puzzle.codex(), "__a1k_");
+        assert!(Puzzle::from_codex("__a1k", 4, 4).is_err());
+        assert!(format!("{}", puzzle).starts_with("# # . 1 \n"));
+
+        // the blocked cells move along with the cells
+        let rotated = puzzle.rotate();
+        assert!(rotated.blocked(3, 0) && rotated.blocked(3, 1) && rotated.blocked(0, 3));
+        assert_eq!(rotated.rotate().rotate().rotate(), puzzle);
+        assert_eq!(puzzle.canonical(), rotated.canonical());
+
+        let plain = Puzzle::from_codex("c1l", 4, 4).unwrap();
+        assert_ne!(puzzle.canonical_hash(), plain.canonical_hash());
+
+        puzzle.set_blocked(3, 0, true);
+        assert_eq!(puzzle.givens(), 0);
+        puzzle.set_blocked(0, 0, false);
+        assert_eq!(puzzle.codex(), "a_a_k_");
+    }
+
+    #[test]
+    fn clues() {
+        let puzzle = Puzzle::from_codex("c1l::2,,,1,,,3,", 4, 4).unwrap();
+        assert_eq!(puzzle.clues(), 3);
+        assert_eq!(puzzle.row_clue(0), Some(2));
+        assert_eq!(puzzle.row_clue(1), None);
+        assert_eq!(puzzle.column_clue(2), Some(3));
+        assert_eq!(puzzle.codex(), "c1l::2,,,1,,,3,");
+        assert!(Puzzle::from_codex("c1l::2,,,1", 4, 4).is_err());
+        assert!(Puzzle::from_codex("c1l::a,,,,,,,", 4, 4).is_err());
+        assert!(Puzzle::from_codex("c1l::,,,,,,,:", 4, 4).is_err());
+        let marked = Puzzle::from_codex("1e1h1:0b0g1l:,,,,,,,2", 4, 4).unwrap();
+        assert_eq!(marked.codex(), "1e1h1:0b0g1l:,,,,,,,2");
+
+        // the clues move along with the lines, and count the zeros when swapped
+        let rotated = puzzle.rotate();
+        assert_eq!(rotated.column_clue(3), Some(2));
+        assert_eq!(rotated.row_clue(2), Some(3));
+        assert_eq!(rotated.rotate().rotate().rotate(), puzzle);
+        assert_eq!(puzzle.swap().row_clue(3), Some(3));
+        assert_eq!(puzzle.swap().swap(), puzzle);
+        assert_eq!(puzzle.canonical(), rotated.canonical());
+
+        let plain = Puzzle::from_codex("c1l", 4, 4).unwrap();
+        assert_ne!(puzzle.canonical_hash(), plain.canonical_hash());
+        assert!(format!("{}", puzzle).starts_with(". . . 1 | 2\n"));
+    }
+
+    #[test]
+    fn trinairo() {
+        let rules = rules::Rules::trinairo();
+        let puzzle = Puzzle::from_codex_with("2a1h0x", 6, 6, rules).unwrap();
+        assert_eq!(puzzle[0][0], Some(2));
+        assert_eq!(puzzle.codex(), "2a1h0x");
+        assert!(format!("{}", puzzle).starts_with("2 . 1 . . . \n"));
+        assert!(Puzzle::from_codex_with("3zi", 6, 6, rules).is_err());
+        assert!(Puzzle::from_codex_with("zj::2,,,,,,,,,,,", 6, 6, rules).is_err());
+        assert!(Puzzle::from_codex("2zi", 6, 6).is_err());
+
+        // any permutation of the symbols is the same game
+        assert_eq!(puzzle.swap().codex(), "0a1h2x");
+        let permuted = Puzzle::from_codex_with("1a0h2x", 6, 6, rules).unwrap();
+        assert_eq!(permuted.canonical(), puzzle.canonical());
+        assert_eq!(permuted.canonical_hash(), puzzle.canonical_hash());
+        let binary = Puzzle::from_codex("1a0h1x", 6, 6).unwrap();
+        assert_ne!(binary.canonical_hash(), puzzle.canonical_hash());
+    }
+
+    #[test]
+    fn transforms() {
+        let puzzle = Puzzle::from_codex("1a0c0e1a1i", 6, 4).unwrap();
+        assert_eq!(puzzle.rotate().codex(), "a101e1a0l");
+        assert_eq!(puzzle.transpose().codex(), "101e0a1m");
+        assert_eq!(puzzle.flip_horizontal().codex(), "c0a1e0c1a1f");
+        assert_eq!(puzzle.flip_vertical().codex(), "f1a1c0e1a0c");
+        assert_eq!(puzzle.swap().codex(), "0a1c1e0a0i");
+
+        assert_eq!(puzzle.rotate().width(), 4);
+        assert_eq!(puzzle.rotate().height(), 6);
+        assert_eq!(puzzle.rotate().rotate().rotate().rotate(), puzzle);
+        assert_eq!(puzzle.transpose().transpose(), puzzle);
+        assert_eq!(puzzle.flip_horizontal().flip_horizontal(), puzzle);
+        assert_eq!(puzzle.flip_vertical().flip_vertical(), puzzle);
+        assert_eq!(puzzle.swap().swap(), puzzle);
+        assert_eq!(puzzle.rotate(), puzzle.transpose().flip_horizontal());
+    }
+
+    #[test]
+    fn canonical() {
+        let puzzle = Puzzle::from_codex("1a0m", 4, 4).unwrap();
+        let images = ["m0a1", "0a1m", "1g0g", "a0a1l", "l1a0a"];
+        for codex in images {
+            let image = Puzzle::from_codex(codex, 4, 4).unwrap();
+            assert_eq!(image.canonical(), puzzle.canonical());
+            assert_eq!(image.canonical_hash(), puzzle.canonical_hash());
+        }
+
+        let other = Puzzle::from_codex("11n", 4, 4).unwrap();
+        assert_ne!(other.canonical(), puzzle.canonical());
+        assert_ne!(other.canonical_hash(), puzzle.canonical_hash());
+        assert_eq!(puzzle.canonical().canonical(), puzzle.canonical());
+    }
+
+    #[test]
+    fn canonical_labels() {
+        // numbering the symbols in order of appearance gives the same canonical
+        // form as going through all their permutations
+        let brute = |puzzle: &Puzzle| {
+            let symbols = puzzle.rules.symbols as usize;
+            let mut images = Vec::new();
+            for transpose in [false, true] {
+                for flip_x in [false, true] {
+                    for flip_y in [false, true] {
+                        for permutation in puzzle.order().into_iter().permutations(symbols) {
+                            images.push(puzzle.image(transpose, flip_x, flip_y, &permutation));
+                        }
+                    }
+                }
+            }
+            images.into_iter().min().unwrap()
+        };
+
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        for symbols in [2, 3, 4] {
+            let rules = rules::Rules {
+                symbols,
+                ..Default::default()
+            };
+            let size = 4 * symbols as usize;
+            for _ in 0..10 {
+                let mut puzzle = Puzzle::new_with(size, size, rules).unwrap();
+                for y in 0..size {
+                    for x in 0..size {
+                        if rng.gen_bool(0.1) {
+                            puzzle[y][x] = Some(rng.gen_range(0..symbols));
+                        }
+                    }
+                }
+                assert_eq!(puzzle.canonical(), brute(&puzzle));
+            }
+        }
+
+        // without values only the clues tell the symbols apart
+        let puzzle = Puzzle::from_codex("p::1,,,,,,3,", 4, 4).unwrap();
+        assert_eq!(puzzle.canonical(), brute(&puzzle));
+        assert_eq!(puzzle.canonical(), puzzle.swap().canonical());
+
+        // ten symbols are too many to go through all their permutations
+        let rules = rules::Rules {
+            symbols: 10,
+            balance: rules::Balance::Free,
+            ..Default::default()
+        };
+        let mut puzzle = Puzzle::new_with(10, 10, rules).unwrap();
+        for i in 0..10 {
+            puzzle[i][i] = Some(9 - i as u8);
+        }
+        let canonical = puzzle.canonical();
+        assert_eq!(canonical[0][9], Some(0));
+        assert_eq!(canonical[9][0], Some(9));
+    }
+
+    #[test]
+    fn canonical_rectangular() {
+        let puzzle = Puzzle::from_codex("1a0c0e1a1i", 6, 4).unwrap();
+        let canonical = puzzle.canonical();
+        assert_eq!(canonical.width(), 6);
+        assert_eq!(canonical.height(), 4);
+        assert_eq!(canonical.givens(), puzzle.givens());
+        assert_eq!(
+            puzzle.image(false, true, true, &[1, 0]).canonical(),
+            canonical
+        );
+    }
+
+    #[test]
+    fn givens() {
+        assert_eq!(Puzzle::new(4, 4).unwrap().givens(), 0);
+        assert_eq!(Puzzle::from_codex("a1d11d1d0f", 6, 4).unwrap().givens(), 5);
+    }
+
     #[test]
     fn small_size() {
         assert!(Puzzle::from_codex("b", 0, 2).is_err());
@@ -0,0 +1,333 @@
+use std::{ops, result};
+
+/// How many times each symbol has to appear in every line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Balance {
+    Exact,   // every symbol appears equally often, so lines have an even length
+    Relaxed, // the numbers of times the symbols appear differ by at most one
+    Free,    // the symbols can appear any number of times
+}
+
+/// Which rules of the rows and columns also hold on the two main diagonals of a
+/// square grid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Diagonals {
+    pub runs: bool,    // the diagonals have no longer runs than the other lines
+    pub balance: bool, // the diagonals are as balanced as the other lines
+}
+
+impl Diagonals {
+    /// Return whether the diagonals follow any of the rules.
+    pub fn any(&self) -> bool {
+        self.runs || self.balance
+    }
+}
+
+/// The rules which the solution of a puzzle has to adhere to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rules {
+    pub run: Option<usize>, // the most consecutive equal values in a line, if limited
+    pub balance: Balance,
+    pub distinct: bool, // no two equal rows or columns
+    pub wrap: bool,     // whether runs continue from the end of a line at its start
+    pub symbols: u8,    // the number of different values, counting up from 0
+    pub diagonals: Diagonals,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            run: Some(2),
+            balance: Balance::Exact,
+            distinct: true,
+            wrap: false,
+            symbols: 2,
+            diagonals: Diagonals::default(),
+        }
+    }
+}
+
+impl Rules {
+    /// The rules of the variant where lines can have an odd length.
+    pub fn relaxed() -> Self {
+        Rules {
+            balance: Balance::Relaxed,
+            ..Default::default()
+        }
+    }
+
+    /// The rules of the Unruly variant, where lines do not have to be distinct.
+    pub fn unruly() -> Self {
+        Rules {
+            distinct: false,
+            ..Default::default()
+        }
+    }
+
+    /// The rules of the toroidal variant, where the grid wraps around its edges.
+    pub fn toroidal() -> Self {
+        Rules {
+            wrap: true,
+            ..Default::default()
+        }
+    }
+
+    /// The rules of the trinairo variant, which has three symbols.
+    pub fn trinairo() -> Self {
+        Rules {
+            symbols: 3,
+            ..Default::default()
+        }
+    }
+
+    /// The rules of the diagonal variant, where the two main diagonals of a
+    /// square grid have no long runs and are balanced as well.
+    pub fn diagonal() -> Self {
+        Rules {
+            diagonals: Diagonals {
+                runs: true,
+                balance: true,
+            },
+            ..Default::default()
+        }
+    }
+
+    /// The rules of the variant where the lines are not balanced, instead clues
+    /// give the number of ones in some of the lines.
+    pub fn clued() -> Self {
+        Rules {
+            balance: Balance::Free,
+            ..Default::default()
+        }
+    }
+
+    /// Return the range of the number of times each symbol can appear in a line
+    /// of the given length, which is empty when the exact balance is asked for
+    /// in a line of which the length is not a multiple of the number of symbols.
+    pub fn count(&self, length: usize) -> ops::RangeInclusive<usize> {
+        let symbols = self.symbols as usize;
+        match self.balance {
+            Balance::Exact => length.div_ceil(symbols)..=length / symbols,
+            Balance::Relaxed => length / symbols..=length.div_ceil(symbols),
+            Balance::Free => 0..=length,
+        }
+    }
+
+    /// Split a line with the given blocked cells into the segments between them,
+    /// which follow the rules on their own. A segment is given by the indices of
+    /// its cells in order. When the line wraps the segments can continue from the
+    /// end at the start, and only a line without blocked cells wraps as a whole.
+    pub fn segments(&self, blocked: &[bool]) -> Vec<Vec<usize>> {
+        let length = blocked.len();
+        let start = match self.wrap {
+            true => blocked.iter().position(|&b| b).map_or(0, |i| i + 1),
+            false => 0,
+        };
+
+        let mut segments = Vec::new();
+        let mut segment = Vec::new();
+        for i in (start..start + length).map(|i| i % length) {
+            if !blocked[i] {
+                segment.push(i);
+            } else if !segment.is_empty() {
+                segments.push(std::mem::take(&mut segment));
+            }
+        }
+        if !segment.is_empty() {
+            segments.push(segment);
+        }
+        return segments;
+    }
+
+    /// Return the rules which a segment of the given line follows, a segment
+    /// only wraps around when it is the whole line.
+    pub fn segment(&self, segment: &[usize], blocked: &[bool]) -> Rules {
+        Rules {
+            wrap: self.wrap && segment.len() == blocked.len(),
+            ..*self
+        }
+    }
+
+    /// Return the rules which a main diagonal follows, which only limits the runs
+    /// and the balance when the diagonals are asked to. A diagonal does not wrap
+    /// and is not compared to other lines.
+    pub fn on_diagonal(&self) -> Rules {
+        Rules {
+            run: self.run.filter(|_| self.diagonals.runs),
+            balance: match self.diagonals.balance {
+                true => self.balance,
+                false => Balance::Free,
+            },
+            distinct: false,
+            wrap: false,
+            ..*self
+        }
+    }
+
+    /// Return a number which identifies the rules, where the default rules are
+    /// zero.
+    pub fn code(&self) -> u64 {
+        return self.balance as u64
+            | (self.run.is_none() as u64) << 2
+            | (!self.distinct as u64) << 3
+            | (self.wrap as u64) << 4
+            | (self.run.unwrap_or(2) as u64 ^ 2) << 5
+            | (self.symbols as u64 ^ 2) << 12
+            | (self.diagonals.runs as u64) << 16
+            | (self.diagonals.balance as u64) << 17;
+    }
+
+    /// Check whether a puzzle with the given sizes is possible with these rules.
+    pub fn check(&self, width: usize, height: usize) -> result::Result<(), String> {
+        if !(2..=10).contains(&self.symbols) {
+            return Err("The number of symbols has to be from two up to ten.".to_string());
+        }
+        let symbols = self.symbols as usize;
+        if self.balance == Balance::Exact
+            && (!width.is_multiple_of(symbols) || !height.is_multiple_of(symbols))
+        {
+            return Err(match symbols {
+                2 => "Width and height have to be a multiple of two.".to_string(),
+                _ => format!("Width and height have to be a multiple of {}.", symbols),
+            });
+        }
+        if width < 4 || height < 4 {
+            return Err("Width and height have to be at least four.".to_string());
+        }
+        // the tactics write a line as a number in the base of the symbols, so
+        // all the lines of this length have to fit in a u64
+        let longest = (1u128 << 64).ilog(symbols as u128) as usize;
+        if width.max(height) > longest {
+            return Err(format!(
+                "Lines can be at most {} cells long with {} symbols.",
+                longest, symbols
+            ));
+        }
+        if self.run == Some(0) {
+            return Err("The maximum run length has to be at least one.".to_string());
+        }
+        if self.diagonals.any() && width != height {
+            return Err("Only square puzzles can have rules on the diagonals.".to_string());
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        assert_eq!(Rules::default().count(6), 3..=3);
+        assert_eq!(Rules::relaxed().count(6), 3..=3);
+        assert!(Rules::default().count(5).is_empty());
+        assert_eq!(Rules::relaxed().count(7), 3..=4);
+        let free = Rules {
+            balance: Balance::Free,
+            ..Default::default()
+        };
+        assert_eq!(free.count(7), 0..=7);
+        assert_eq!(Rules::clued().count(6), 0..=6);
+        assert_eq!(Rules::trinairo().count(6), 2..=2);
+        assert!(Rules::trinairo().count(8).is_empty());
+    }
+
+    #[test]
+    fn codes() {
+        assert_eq!(Rules::default().code(), 0);
+        assert_eq!(Rules::relaxed().code(), 1);
+        assert_ne!(Rules::unruly().code(), 0);
+        assert_ne!(Rules::trinairo().code(), 0);
+        assert_ne!(Rules::diagonal().code(), 0);
+        assert_ne!(
+            Rules::diagonal().code(),
+            Rules::diagonal().on_diagonal().code()
+        );
+        let toggles = (0..24).map(|i| Rules {
+            run: [None, Some(1), Some(2), Some(3), Some(4), Some(5)][i % 6],
+            balance: Balance::Exact,
+            distinct: i % 12 < 6,
+            wrap: i < 12,
+            symbols: 2,
+            diagonals: Diagonals::default(),
+        });
+        let codes: std::collections::HashSet<_> = toggles.map(|rules| rules.code()).collect();
+        assert_eq!(codes.len(), 24);
+    }
+
+    #[test]
+    fn segments() {
+        let blocked = [false, false, true, false, true, true, false];
+        assert_eq!(
+            Rules::default().segments(&blocked),
+            vec![vec![0, 1], vec![3], vec![6]]
+        );
+        assert_eq!(
+            Rules::toroidal().segments(&blocked),
+            vec![vec![3], vec![6, 0, 1]]
+        );
+        assert_eq!(
+            Rules::toroidal().segments(&[false; 4]),
+            vec![vec![0, 1, 2, 3]]
+        );
+        assert_eq!(
+            Rules::default().segments(&[true; 4]),
+            Vec::<Vec<usize>>::new()
+        );
+        assert!(Rules::toroidal().segment(&[0, 1, 2, 3], &[false; 4]).wrap);
+        assert!(!Rules::toroidal().segment(&[6, 0, 1], &blocked).wrap);
+    }
+
+    #[test]
+    fn sizes() {
+        assert!(Rules::default().check(6, 4).is_ok());
+        assert!(Rules::default().check(5, 4).is_err());
+        assert!(Rules::relaxed().check(5, 7).is_ok());
+        assert!(Rules::relaxed().check(3, 6).is_err());
+        assert!(Rules::default().check(30, 4).is_ok());
+        assert!(Rules::default().check(64, 4).is_ok());
+        assert!(Rules::default().check(66, 4).is_err());
+        assert!(Rules::relaxed().check(5, 65).is_err());
+        let rules = Rules {
+            run: Some(0),
+            ..Default::default()
+        };
+        assert!(rules.check(6, 6).is_err());
+        assert!(Rules::trinairo().check(6, 9).is_ok());
+        assert!(Rules::trinairo().check(6, 8).is_err());
+        assert!(Rules::trinairo().check(39, 30).is_ok());
+        assert!(Rules::trinairo().check(42, 30).is_err());
+        let rules = Rules {
+            symbols: 10,
+            ..Default::default()
+        };
+        assert!(rules.check(10, 10).is_ok());
+        assert!(rules.check(10, 20).is_err());
+        let rules = Rules {
+            symbols: 1,
+            ..Default::default()
+        };
+        assert!(rules.check(6, 6).is_err());
+        assert!(Rules::diagonal().check(6, 6).is_ok());
+        assert!(Rules::diagonal().check(6, 8).is_err());
+    }
+
+    #[test]
+    fn diagonals() {
+        let rules = Rules::diagonal().on_diagonal();
+        assert_eq!(rules.run, Some(2));
+        assert_eq!(rules.balance, Balance::Exact);
+        assert!(!rules.distinct);
+        let rules = Rules {
+            diagonals: Diagonals {
+                runs: true,
+                balance: false,
+            },
+            ..Rules::toroidal()
+        };
+        assert_eq!(rules.on_diagonal().balance, Balance::Free);
+        assert!(!rules.on_diagonal().wrap);
+        assert_eq!(Rules::default().on_diagonal().run, None);
+    }
+}
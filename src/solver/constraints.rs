@@ -1,4 +1,8 @@
-use crate::puzzle;
+use crate::puzzle::{
+    self,
+    line::{self, Line},
+    marker, rules,
+};
 use z3::{self, ast::Ast};
 
 /// Add constraints so that the given solution will not be found again.
@@ -6,16 +10,18 @@ pub fn solution(
     ctx: &z3::Context,
     solver: &z3::Solver,
     solution: &puzzle::Puzzle,
-    shadow: &Vec<Vec<z3::ast::Int>>,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
 ) {
     let mut z3solution = Vec::new();
 
     for y in 0..solution.height() {
-        for x in 0..solution.width() {
-            z3solution.push(shadow[y][x]._eq(&z3::ast::Int::from_u64(
-                &ctx,
-                solution[y][x].unwrap() as u64,
-            )));
+        for x in (0..solution.width()).filter(|&x| !solution.blocked(x, y)) {
+            for (v, cell) in (1..).zip(&shadow[y][x]) {
+                z3solution.push(cell._eq(&z3::ast::Bool::from_bool(
+                    &ctx,
+                    solution[y][x].unwrap() == v,
+                )));
+            }
         }
     }
 
@@ -26,149 +32,245 @@ pub fn solution(
     solver.assert(&z3::ast::Bool::and(&ctx, &z3solution).not());
 }
 
-/// Add all the binairo puzzle constraints to the provided solver.
+/// Add all the binairo puzzle constraints of the rules of the puzzle to the
+/// provided solver. Every cell has a boolean for every symbol but the 0 which is
+/// true for that symbol, so with two symbols a cell is one boolean which is true
+/// for a 1, such that the solver can reason about the cells with clauses and
+/// pseudo-boolean constraints instead of arithmetic.
 pub fn all(
     ctx: &z3::Context,
     solver: &z3::Solver,
     puzzle: &puzzle::Puzzle,
-    shadow: &Vec<Vec<z3::ast::Int>>,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
 ) {
+    let rules = puzzle.rules();
+
     constraint_numbers(ctx, solver, puzzle, shadow);
     constraint_consecutive(ctx, solver, puzzle, shadow);
     constraint_balance(ctx, solver, puzzle, shadow);
-    constraint_uniqueness(ctx, solver, puzzle, shadow);
+    if rules.distinct {
+        constraint_uniqueness(ctx, solver, puzzle, shadow);
+    }
+    constraint_markers(ctx, solver, puzzle, shadow);
+    constraint_clues(ctx, solver, puzzle, shadow);
 }
 
-/// Add the given puzzle constraints and make sure every cell is a 0 or 1.
+/// Add the given puzzle constraints.
 fn constraint_numbers(
     ctx: &z3::Context,
     solver: &z3::Solver,
     puzzle: &puzzle::Puzzle,
-    shadow: &Vec<Vec<z3::ast::Int>>,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
 ) {
     for y in 0..puzzle.height() {
         for x in 0..puzzle.width() {
+            // A cell has at most one of the symbols other than 0
+            if shadow[y][x].len() > 1 {
+                let cell = shadow[y][x].iter().map(|v| (v, 1)).collect::<Vec<_>>();
+                solver.assert(&z3::ast::Bool::pb_le(ctx, &cell, 1));
+            }
+
             // Some cells are according to the given puzzle a 0 or 1
             if let Some(value) = puzzle[y][x] {
-                solver.assert(&shadow[y][x]._eq(&z3::ast::Int::from_u64(ctx, value as u64)))
-            } else {
-                // Every other cell is either a 0 or a 1
-                solver.assert(&z3::ast::Bool::or(
-                    ctx,
-                    &[
-                        &shadow[y][x]._eq(&z3::ast::Int::from_u64(ctx, 0)),
-                        &shadow[y][x]._eq(&z3::ast::Int::from_u64(ctx, 1)),
-                    ],
-                ))
+                for (v, cell) in (1..).zip(&shadow[y][x]) {
+                    solver.assert(&cell._eq(&z3::ast::Bool::from_bool(ctx, value == v)))
+                }
             }
         }
     }
 }
 
-/// Make sure there are no more than two consecutive 0's or 1's anywhere.
+/// Make sure there are no more than the longest allowed number of consecutive
+/// 0's or 1's in any segment, also around the edges when the grid wraps.
 fn constraint_consecutive(
     ctx: &z3::Context,
     solver: &z3::Solver,
     puzzle: &puzzle::Puzzle,
-    shadow: &Vec<Vec<z3::ast::Int>>,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
 ) {
-    // No more than 2 consecutive 0's or 1's per row
-    for y in 0..puzzle.height() {
-        for x in 0..puzzle.width() - 2 {
-            let sum =
-                &z3::ast::Int::add(ctx, &[&shadow[y][x], &shadow[y][x + 1], &shadow[y][x + 2]]);
-            solver.assert(&z3::ast::Bool::or(
-                ctx,
-                &[
-                    &sum._eq(&z3::ast::Int::from_i64(ctx, 1)),
-                    &sum._eq(&z3::ast::Int::from_i64(ctx, 2)),
-                ],
-            ));
+    let consecutive = |cells: Vec<&Vec<z3::ast::Bool>>| {
+        // at least one of the cells is not a 0, and for every other symbol at
+        // least one of the cells is not that symbol
+        let all = cells
+            .iter()
+            .flat_map(|cell| cell.iter())
+            .collect::<Vec<_>>();
+        solver.assert(&z3::ast::Bool::or(ctx, &all));
+        for v in 0..cells[0].len() {
+            let symbol = cells.iter().map(|cell| &cell[v]).collect::<Vec<_>>();
+            solver.assert(&z3::ast::Bool::and(ctx, &symbol).not());
         }
-    }
+    };
 
-    // No more than 2 consecutive 0's or 1's per column
-    for x in 0..puzzle.width() {
-        for y in 0..puzzle.height() - 2 {
-            let sum =
-                &z3::ast::Int::add(ctx, &[&shadow[y][x], &shadow[y + 1][x], &shadow[y + 2][x]]);
-            solver.assert(&z3::ast::Bool::or(
-                ctx,
-                &[
-                    &sum._eq(&z3::ast::Int::from_i64(ctx, 1)),
-                    &sum._eq(&z3::ast::Int::from_i64(ctx, 2)),
-                ],
-            ));
+    // No more than the longest run of 0's or 1's per segment of every row and
+    // column, and of the diagonals if they follow the rule
+    for segment in segments(puzzle) {
+        let longest = match segment.rules.run {
+            Some(longest) => longest,
+            None => continue,
+        };
+        let length = segment.cells.len();
+        let starts = match segment.rules.wrap {
+            true => length,
+            false => (length + 1).saturating_sub(longest + 1),
+        };
+        for i in 0..starts {
+            consecutive(
+                (i..=i + longest)
+                    .map(|j| segment.position(j % length))
+                    .map(|(x, y)| &shadow[y][x])
+                    .collect(),
+            );
         }
     }
 }
 
-/// Make sure every row and column has the same number of 0's as 1's.
+/// Make sure every segment of the rows and columns, and of the diagonals if
+/// they follow the rule, has the same number of 0's as 1's, or for the relaxed
+/// balance that the numbers differ by at most one. With more symbols every
+/// symbol appears the same number of times.
 fn constraint_balance(
     ctx: &z3::Context,
     solver: &z3::Solver,
     puzzle: &puzzle::Puzzle,
-    shadow: &Vec<Vec<z3::ast::Int>>,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
 ) {
-    // Same number of 0's and 1's per row
-    for y in 0..puzzle.height() {
-        let row = (0..puzzle.width())
-            .map(|x| &shadow[y][x])
-            .collect::<Vec<_>>();
-
-        solver.assert(
-            &z3::ast::Int::add(ctx, row.as_slice())
-                ._eq(&z3::ast::Int::from_u64(ctx, puzzle.width() as u64 / 2)),
-        );
-    }
-
-    // Same number of 0's and 1's per column
-    for x in 0..puzzle.width() {
-        let column = (0..puzzle.height())
-            .map(|y| &shadow[y][x])
-            .collect::<Vec<_>>();
+    let balance = |line: Vec<&z3::ast::Bool>, (low, high): (usize, usize)| {
+        let line = line.into_iter().map(|cell| (cell, 1)).collect::<Vec<_>>();
+        if low == high {
+            solver.assert(&z3::ast::Bool::pb_eq(ctx, &line, low as i32));
+        } else {
+            solver.assert(&z3::ast::Bool::pb_ge(ctx, &line, low as i32));
+            solver.assert(&z3::ast::Bool::pb_le(ctx, &line, high as i32));
+        }
+    };
 
-        solver.assert(
-            &z3::ast::Int::add(ctx, column.as_slice())
-                ._eq(&z3::ast::Int::from_u64(ctx, puzzle.height() as u64 / 2)),
-        );
+    // Same number of 0's and 1's per segment of every row and column, where the
+    // number of 0's follows from the number of the other symbols
+    let symbols = puzzle.rules().symbols as usize;
+    for segment in segments(puzzle) {
+        if segment.rules.balance == rules::Balance::Free {
+            continue;
+        }
+        let cells: Vec<_> = (0..segment.cells.len())
+            .map(|i| segment.position(i))
+            .collect();
+        let (low, high) = segment.rules.count(cells.len()).into_inner();
+        for v in 0..symbols - 1 {
+            balance(
+                cells.iter().map(|&(x, y)| &shadow[y][x][v]).collect(),
+                (low, high),
+            );
+        }
+        if symbols > 2 {
+            balance(
+                cells.iter().flat_map(|&(x, y)| &shadow[y][x]).collect(),
+                (cells.len() - high.min(cells.len()), cells.len() - low),
+            );
+        }
     }
 }
 
-/// Make sure that every row is unique, and every column is unique.
+/// Make sure that every row is unique, and every column is unique. Lines with
+/// different blocked cells are always different, and so are not compared.
 fn constraint_uniqueness(
     ctx: &z3::Context,
     solver: &z3::Solver,
     puzzle: &puzzle::Puzzle,
-    shadow: &Vec<Vec<z3::ast::Int>>,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
 ) {
-    // Unique rows
-    for y1 in 0..puzzle.height() {
-        for y2 in y1 + 1..puzzle.height() {
-            let compare = (0..puzzle.width())
-                .map(|x| shadow[y1][x]._eq(&shadow[y2][x]).not())
-                .collect::<Vec<_>>();
+    let blocked = |&(x, y): &(usize, usize)| puzzle.blocked(x, y);
 
-            let compare = (0..compare.len())
-                .map(|index| &compare[index])
-                .collect::<Vec<_>>();
+    // Unique rows and unique columns
+    for lines in [Line::Row(0), Line::Column(0)] {
+        let lines: Vec<_> = lines
+            .parallel(puzzle)
+            .iter()
+            .map(|l| l.positions(puzzle))
+            .collect();
+        for (i, line1) in lines.iter().enumerate() {
+            for line2 in &lines[i + 1..] {
+                if !line1.iter().map(blocked).eq(line2.iter().map(blocked))
+                    || line1.iter().all(blocked)
+                {
+                    continue;
+                }
 
-            solver.assert(&z3::ast::Bool::or(ctx, compare.as_slice()));
+                let compare = line1
+                    .iter()
+                    .zip(line2)
+                    .filter(|(cell, _)| !blocked(cell))
+                    .flat_map(|(&(x1, y1), &(x2, y2))| {
+                        shadow[y1][x1]
+                            .iter()
+                            .zip(&shadow[y2][x2])
+                            .map(|(a, b)| a.xor(b))
+                    })
+                    .collect::<Vec<_>>();
+
+                let compare = (0..compare.len())
+                    .map(|index| &compare[index])
+                    .collect::<Vec<_>>();
+
+                solver.assert(&z3::ast::Bool::or(ctx, compare.as_slice()));
+            }
         }
     }
+}
 
-    // Unique columns
-    for x1 in 0..puzzle.width() {
-        for x2 in x1 + 1..puzzle.width() {
-            let compare = (0..puzzle.height())
-                .map(|y| shadow[y][x1]._eq(&shadow[y][x2]).not())
+/// Make sure the rows and columns with a clue have the given number of 1's.
+fn constraint_clues(
+    ctx: &z3::Context,
+    solver: &z3::Solver,
+    puzzle: &puzzle::Puzzle,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
+) {
+    for line in line::all(puzzle) {
+        if let Some(clue) = line.clue(puzzle) {
+            let line = line
+                .positions(puzzle)
+                .into_iter()
+                .filter(|&(x, y)| !puzzle.blocked(x, y))
+                .map(|(x, y)| (&shadow[y][x][0], 1))
                 .collect::<Vec<_>>();
+            solver.assert(&z3::ast::Bool::pb_eq(ctx, &line, clue as i32));
+        }
+    }
+}
 
-            let compare = (0..compare.len())
-                .map(|index| &compare[index])
-                .collect::<Vec<_>>();
+/// Return the segments of all the rows, columns and the main diagonals if the
+/// rules apply to them, between their blocked cells.
+fn segments(puzzle: &puzzle::Puzzle) -> Vec<line::Segment> {
+    line::all(puzzle)
+        .into_iter()
+        .flat_map(|l| l.segments(puzzle))
+        .collect()
+}
 
-            solver.assert(&z3::ast::Bool::or(ctx, compare.as_slice()));
+/// Make sure the neighbours with a marker between them are equal or opposite.
+fn constraint_markers(
+    ctx: &z3::Context,
+    solver: &z3::Solver,
+    puzzle: &puzzle::Puzzle,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
+) {
+    for (x, y, direction) in puzzle.edges() {
+        let (nx, ny) = direction.neighbour(x, y);
+        let pairs = shadow[y][x].iter().zip(&shadow[ny][nx]);
+        match puzzle.marker(x, y, direction) {
+            Some(marker::Marker::Equal) => {
+                for (a, b) in pairs {
+                    solver.assert(&a._eq(b));
+                }
+            }
+            Some(marker::Marker::Opposite) => {
+                let differ = pairs.map(|(a, b)| a.xor(b)).collect::<Vec<_>>();
+                match differ.as_slice() {
+                    [differ] => solver.assert(differ),
+                    _ => solver.assert(&z3::ast::Bool::or(ctx, &differ.iter().collect::<Vec<_>>())),
+                }
+            }
+            None => (),
         }
     }
 }
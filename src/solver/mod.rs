@@ -43,12 +43,43 @@ pub fn unique(puzzle: &puzzle::Puzzle) -> Option<bool> {
     }
 }
 
-/// Initialize a 2d array with z3 objects.
-fn init<'a>(ctx: &'a z3::Context, puzzle: &puzzle::Puzzle) -> Vec<Vec<z3::ast::Int<'a>>> {
+/// Checks whether the given puzzle has one unique solution, and whether every
+/// given value is required for this, i.e. no given value can be left out.
+pub fn is_minimal(puzzle: &puzzle::Puzzle) -> bool {
+    if unique(puzzle) != Some(true) {
+        return false;
+    }
+
+    let mut puzzle = puzzle.clone();
+    for y in 0..puzzle.height() {
+        for x in 0..puzzle.width() {
+            if let Some(value) = puzzle[y][x].take() {
+                if unique(&puzzle) == Some(true) {
+                    return false;
+                }
+                puzzle[y][x] = Some(value);
+            }
+        }
+    }
+    return true;
+}
+
+/// Initialize a 2d array with z3 objects, one for every symbol but the 0 in
+/// every cell.
+fn init<'a>(ctx: &'a z3::Context, puzzle: &puzzle::Puzzle) -> Vec<Vec<Vec<z3::ast::Bool<'a>>>> {
+    let symbols = puzzle.rules().symbols;
+    let name = |x: usize, y: usize, v: u8| match symbols {
+        2 => format!("x{}y{}", x, y),
+        _ => format!("x{}y{}v{}", x, y, v),
+    };
     (0..puzzle.height())
         .map(|y| {
             (0..puzzle.width())
-                .map(|x| z3::ast::Int::new_const(ctx, format!("x{}y{}", x, y)))
+                .map(|x| {
+                    (1..symbols)
+                        .map(|v| z3::ast::Bool::new_const(ctx, name(x, y, v)))
+                        .collect()
+                })
                 .collect()
         })
         .collect()
@@ -58,15 +89,18 @@ fn init<'a>(ctx: &'a z3::Context, puzzle: &puzzle::Puzzle) -> Vec<Vec<z3::ast::I
 fn extract(
     solver: &z3::Solver,
     puzzle: &puzzle::Puzzle,
-    shadow: &Vec<Vec<z3::ast::Int>>,
+    shadow: &Vec<Vec<Vec<z3::ast::Bool>>>,
 ) -> puzzle::Puzzle {
-    let mut solution = puzzle::Puzzle::new(puzzle.width(), puzzle.height()).unwrap();
+    let mut solution = puzzle.clone();
     let model = solver.get_model().unwrap();
 
     for y in 0..puzzle.height() {
-        for x in 0..puzzle.width() {
-            let value = model.eval(&shadow[y][x], true).unwrap().as_u64().unwrap();
-            solution[y][x] = Some(value.try_into().unwrap());
+        for x in (0..puzzle.width()).filter(|&x| !puzzle.blocked(x, y)) {
+            let value = (1..)
+                .zip(&shadow[y][x])
+                .find(|(_, cell)| model.eval(*cell, true).unwrap().as_bool().unwrap())
+                .map_or(0, |(v, _)| v);
+            solution[y][x] = Some(value);
         }
     }
     return solution;
@@ -75,6 +109,8 @@ fn extract(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::puzzle::rules;
+    use rand::{Rng, SeedableRng};
 
     /// Test whether the uniqueness test works properly
     #[test]
@@ -100,6 +136,145 @@ mod tests {
         assert!(solve(&puzzle::Puzzle::from_codex("a1c1e0c0", 4, 4).unwrap()).is_none());
     }
 
+    #[test]
+    fn solve_relaxed() {
+        let rules = rules::Rules::relaxed();
+        for (width, height) in [(5, 5), (7, 5), (9, 6)] {
+            let solution = solve(&puzzle::Puzzle::new_with(width, height, rules).unwrap()).unwrap();
+            assert_eq!(solution.givens(), width * height);
+            assert_eq!(solution.rules(), &rules);
+            for y in 0..height {
+                let ones = (0..width).filter(|&x| solution[y][x] == Some(1)).count();
+                assert!(rules.count(width).contains(&ones));
+            }
+            for x in 0..width {
+                let ones = (0..height).filter(|&y| solution[y][x] == Some(1)).count();
+                assert!(rules.count(height).contains(&ones));
+            }
+        }
+    }
+
+    #[test]
+    fn solve_toggles() {
+        let count = |width, height, rules| {
+            let puzzle = puzzle::Puzzle::new_with(width, height, rules).unwrap();
+            solves(&puzzle, None).unwrap().len()
+        };
+        let rules = rules::Rules::default();
+        assert_eq!(count(6, 4, rules), 96);
+        assert_eq!(count(6, 4, rules::Rules::unruly()), 642);
+        let rules = rules::Rules { run: None, ..rules };
+        assert_eq!(count(6, 4, rules), 720);
+        let rules = rules::Rules {
+            balance: rules::Balance::Free,
+            ..rules::Rules::unruly()
+        };
+        assert_eq!(count(4, 4, rules), 2030);
+
+        // equal rows are allowed without the rule
+        let puzzle = puzzle::Puzzle::from_codex_with("11d00h", 4, 4, rules::Rules::unruly());
+        assert!(solve(&puzzle.unwrap()).is_some());
+    }
+
+    #[test]
+    fn solve_markers() {
+        let puzzle = puzzle::Puzzle::from_codex("1e1h1:0b0g1l", 4, 4).unwrap();
+        for solution in solves(&puzzle, None).unwrap() {
+            assert_eq!(solution[0][1], Some(1));
+            assert_eq!(solution[1][0], solution[1][1]);
+            assert_ne!(solution[3][2], solution[3][3]);
+        }
+
+        // equal neighbours with an opposite marker, and the other way around
+        assert!(solve(&puzzle::Puzzle::from_codex("10n:0w", 4, 4).unwrap()).is_none());
+        assert!(solve(&puzzle::Puzzle::from_codex("11n:1w", 4, 4).unwrap()).is_none());
+    }
+
+    #[test]
+    fn solve_runs() {
+        let rules = |longest| rules::Rules {
+            run: Some(longest),
+            ..Default::default()
+        };
+        for (longest, count) in [(1, 0), (2, 96), (3, 720), (4, 720)] {
+            let puzzle = puzzle::Puzzle::new_with(6, 4, rules(longest)).unwrap();
+            assert_eq!(solves(&puzzle, None).map_or(0, |s| s.len()), count);
+        }
+
+        for longest in [3, 4] {
+            let puzzle = puzzle::Puzzle::new_with(10, 10, rules(longest)).unwrap();
+            let solution = solve(&puzzle).unwrap();
+            for y in 0..10 {
+                for x in 0..10 - longest {
+                    let row = (x..=x + longest).map(|x| solution[y][x]);
+                    assert!(row.clone().any(|v| v != solution[y][x]));
+                    let column = (x..=x + longest).map(|i| solution[i][y]);
+                    assert!(column.clone().any(|v| v != solution[x][y]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn solve_wrap() {
+        let rules = rules::Rules::toroidal();
+        let solution = solve(&puzzle::Puzzle::new_with(10, 10, rules).unwrap()).unwrap();
+        for i in 0..10 {
+            for j in 0..10 {
+                let row = [j, (j + 1) % 10, (j + 2) % 10].map(|x| solution[i][x]);
+                assert!(row[0] != row[1] || row[1] != row[2]);
+                let column = [j, (j + 1) % 10, (j + 2) % 10].map(|y| solution[y][i]);
+                assert!(column[0] != column[1] || column[1] != column[2]);
+            }
+        }
+
+        // the runs continue around the edges
+        let puzzle = puzzle::Puzzle::from_codex_with("0f0zn", 8, 6, rules).unwrap();
+        for solution in solves(&puzzle, Some(20)).unwrap() {
+            assert_eq!(solution[0][6], Some(1));
+            assert_eq!(solution[0][1], Some(1));
+        }
+    }
+
+    #[test]
+    fn solve_large() {
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        for (width, height) in [(30, 30), (30, 8), (8, 30), (14, 26)] {
+            let solution = solve(&puzzle::Puzzle::new(width, height).unwrap()).unwrap();
+            assert_eq!(solution.givens(), width * height);
+            assert_eq!(unique(&solution), Some(true));
+
+            // the solution of a part of the solution has the same given values
+            let mut puzzle = solution.clone();
+            for y in 0..height {
+                for x in 0..width {
+                    if rng.gen_bool(0.7) {
+                        puzzle[y][x] = None;
+                    }
+                }
+            }
+            let found = solve(&puzzle).unwrap();
+            assert_eq!(unique(&found), Some(true));
+            for y in 0..height {
+                for x in 0..width {
+                    assert!(puzzle[y][x].is_none() || puzzle[y][x] == found[y][x]);
+                }
+            }
+        }
+
+        // there are only six different valid columns of height four
+        assert!(solve(&puzzle::Puzzle::new(8, 4).unwrap()).is_none());
+        assert!(solve(&puzzle::Puzzle::new(6, 4).unwrap()).is_some());
+    }
+
+    /// Test whether the puzzles where every given is required are recognized
+    #[test]
+    fn solve_minimal() {
+        assert!(!is_minimal(&puzzle::Puzzle::from_codex("11d111010d", 4, 4).unwrap()));
+        assert!(!is_minimal(&puzzle::Puzzle::from_codex("11d11h", 4, 4).unwrap()));
+        assert!(is_minimal(&puzzle::Puzzle::from_codex("f11b1b1a1", 4, 4).unwrap()));
+    }
+
     /// Test whether multiple solutions are found correctly
     #[test]
     fn solve_more() {
@@ -122,4 +297,114 @@ mod tests {
         assert!(solutions("11d11h", Some(3)) == 3);
         assert!(solutions("11d11h", Some(4)) == 4);
     }
+
+    #[test]
+    fn solve_transformed() {
+        let puzzle = puzzle::Puzzle::from_codex("a0d1g1a0a1h1b1a00b", 6, 6).unwrap();
+        let solution = solve(&puzzle).unwrap();
+
+        assert_eq!(solve(&puzzle.rotate()), Some(solution.rotate()));
+        assert_eq!(solve(&puzzle.transpose()), Some(solution.transpose()));
+        assert_eq!(
+            solve(&puzzle.flip_horizontal()),
+            Some(solution.flip_horizontal())
+        );
+        assert_eq!(
+            solve(&puzzle.flip_vertical()),
+            Some(solution.flip_vertical())
+        );
+        assert_eq!(solve(&puzzle.swap()), Some(solution.swap()));
+    }
+
+    #[test]
+    fn solve_blocked() {
+        let puzzle = puzzle::Puzzle::from_codex("__d__dx", 6, 6).unwrap();
+        let solution = solve(&puzzle).unwrap();
+        assert_eq!(solution.givens(), 32);
+        assert_eq!(unique(&solution), Some(true));
+        for i in 0..6 {
+            // the lines next to the blocked corner only consist of their last four cells
+            let start = if i < 2 { 2 } else { 0 };
+            let row: Vec<_> = (start..6).map(|x| solution[i][x].unwrap()).collect();
+            let column: Vec<_> = (start..6).map(|y| solution[y][i].unwrap()).collect();
+            for line in [row, column] {
+                assert_eq!(line.iter().filter(|&&v| v == 1).count(), line.len() / 2);
+                assert!(line.windows(3).all(|w| w[0] != w[1] || w[1] != w[2]));
+            }
+        }
+        assert_ne!(solution[0], solution[1]);
+
+        // a segment of odd length can not be balanced
+        assert!(solve(&puzzle::Puzzle::from_codex("_zi", 6, 6).unwrap()).is_none());
+        let rules = rules::Rules::relaxed();
+        assert!(solve(&puzzle::Puzzle::from_codex_with("_zi", 6, 6, rules).unwrap()).is_some());
+    }
+
+    #[test]
+    fn solve_trinairo() {
+        let rules = rules::Rules::trinairo();
+        let solution = solve(&puzzle::Puzzle::new_with(6, 6, rules).unwrap()).unwrap();
+        assert_eq!(solution.givens(), 36);
+        for i in 0..6 {
+            let row: Vec<_> = (0..6).map(|x| solution[i][x].unwrap()).collect();
+            let column: Vec<_> = (0..6).map(|y| solution[y][i].unwrap()).collect();
+            for line in [row, column] {
+                for v in 0..3 {
+                    assert_eq!(line.iter().filter(|&&w| w == v).count(), 2);
+                }
+                assert!(line.windows(3).all(|w| w[0] != w[1] || w[1] != w[2]));
+            }
+        }
+        assert_eq!(unique(&solution), Some(true));
+
+        // an opposite marker next to a 2 rules out a 2 in the other cell
+        let puzzle = puzzle::Puzzle::from_codex_with("2zi:1zzg", 6, 6, rules).unwrap();
+        for solution in solves(&puzzle, Some(10)).unwrap() {
+            assert_ne!(solution[0][1], Some(2));
+        }
+    }
+
+    #[test]
+    fn solve_diagonal() {
+        let rules = rules::Rules::diagonal();
+        let solution = solve(&puzzle::Puzzle::new_with(8, 8, rules).unwrap()).unwrap();
+        assert_eq!(unique(&solution), Some(true));
+        for diagonal in [[0, 1], [7, -1]] {
+            let line: Vec<_> = (0..8)
+                .map(|i| solution[i][(diagonal[0] + diagonal[1] * i as i32) as usize].unwrap())
+                .collect();
+            assert_eq!(line.iter().filter(|&&v| v == 1).count(), 4);
+            assert!(line.windows(3).all(|w| w[0] != w[1] || w[1] != w[2]));
+        }
+
+        // the diagonals rule out some of the solutions
+        let count = |rules| {
+            let puzzle = puzzle::Puzzle::new_with(4, 4, rules).unwrap();
+            solves(&puzzle, None).unwrap().len()
+        };
+        let runs = rules::Rules {
+            diagonals: rules::Diagonals {
+                runs: true,
+                balance: false,
+            },
+            ..Default::default()
+        };
+        assert!(count(rules) < count(runs));
+        assert!(count(runs) < count(rules::Rules::default()));
+    }
+
+    #[test]
+    fn solve_clues() {
+        let rules = rules::Rules::clued();
+        let puzzle = puzzle::Puzzle::from_codex_with("zj::2,4,3,3,2,4,,,,,,", 6, 6, rules).unwrap();
+        let solution = solve(&puzzle).unwrap();
+        for y in 0..6 {
+            let ones = solution[y].iter().filter(|&&v| v == Some(1)).count();
+            assert_eq!(Some(ones), puzzle.row_clue(y));
+        }
+
+        // five ones in a line of six always make a run of three
+        let puzzle = puzzle::Puzzle::from_codex_with("zj::5,,,,,,,,,,,", 6, 6, rules).unwrap();
+        assert!(solve(&puzzle).is_none());
+    }
 }